@@ -0,0 +1,74 @@
+-- Subset of the Shopsys schema with only the tables and columns the exporter and the web read
+-- After changing the schema or a query, refresh the offline data of the query macros in .sqlx/:
+-- cargo sqlx prepare -- --all-targets
+
+CREATE TABLE products (
+    id SERIAL PRIMARY KEY,
+    catnum VARCHAR(100) NOT NULL,
+    partno VARCHAR(100),
+    ean VARCHAR(100),
+    brand_id INT,
+    variant_type VARCHAR(32) NOT NULL DEFAULT 'none',
+    main_variant_id INT REFERENCES products (id),
+    calculated_selling_denied BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE TABLE product_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES products (id),
+    locale VARCHAR(255) NOT NULL,
+    name VARCHAR(255),
+    name_prefix VARCHAR(255),
+    name_sufix VARCHAR(255)
+);
+CREATE TABLE product_domains (
+    id SERIAL PRIMARY KEY,
+    product_id INT NOT NULL REFERENCES products (id),
+    domain_id INT NOT NULL,
+    description TEXT,
+    short_description TEXT
+);
+CREATE TABLE product_visibilities (
+    product_id INT NOT NULL REFERENCES products (id),
+    pricing_group_id INT NOT NULL,
+    domain_id INT NOT NULL,
+    visible BOOLEAN NOT NULL,
+    PRIMARY KEY (product_id, pricing_group_id, domain_id)
+);
+CREATE TABLE product_domain_flags (
+    product_domain_id INT NOT NULL REFERENCES product_domains (id),
+    flag_id INT NOT NULL,
+    PRIMARY KEY (product_domain_id, flag_id)
+);
+CREATE TABLE categories (
+    id SERIAL PRIMARY KEY,
+    parent_id INT REFERENCES categories (id),
+    level INT NOT NULL,
+    lft INT NOT NULL,
+    rgt INT NOT NULL
+);
+CREATE TABLE category_domains (
+    id SERIAL PRIMARY KEY,
+    category_id INT NOT NULL REFERENCES categories (id),
+    domain_id INT NOT NULL,
+    visible BOOLEAN NOT NULL
+);
+CREATE TABLE product_category_domains (
+    product_id INT NOT NULL REFERENCES products (id),
+    category_id INT NOT NULL REFERENCES categories (id),
+    domain_id INT NOT NULL,
+    PRIMARY KEY (product_id, category_id, domain_id)
+);
+CREATE TABLE category_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES categories (id),
+    name VARCHAR(255) NOT NULL,
+    locale VARCHAR(255) NOT NULL
+);
+CREATE TABLE friendly_urls (
+    domain_id INT NOT NULL,
+    slug TEXT NOT NULL,
+    route_name VARCHAR(255) NOT NULL,
+    entity_id INT NOT NULL,
+    main BOOLEAN NOT NULL,
+    PRIMARY KEY (domain_id, slug)
+);
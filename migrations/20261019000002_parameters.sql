@@ -0,0 +1,14 @@
+CREATE TABLE parameters (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL
+);
+CREATE TABLE parameter_templates (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP(0) WITHOUT TIME ZONE
+);
+CREATE TABLE parameter_templates_parameters (
+    parameter_template_id INT NOT NULL REFERENCES parameter_templates (id),
+    parameter_id INT NOT NULL REFERENCES parameters (id),
+    PRIMARY KEY (parameter_template_id, parameter_id)
+);
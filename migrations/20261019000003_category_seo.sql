@@ -0,0 +1,5 @@
+ALTER TABLE category_domains
+    ADD COLUMN seo_title TEXT,
+    ADD COLUMN seo_meta_description TEXT,
+    ADD COLUMN seo_h1 TEXT,
+    ADD COLUMN description TEXT;
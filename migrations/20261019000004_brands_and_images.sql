@@ -0,0 +1,19 @@
+CREATE TABLE brands (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    url VARCHAR(255)
+);
+CREATE TABLE brand_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES brands (id),
+    locale VARCHAR(255) NOT NULL,
+    description TEXT
+);
+CREATE TABLE images (
+    id SERIAL PRIMARY KEY,
+    entity_name VARCHAR(100) NOT NULL,
+    entity_id INT NOT NULL,
+    type VARCHAR(100),
+    extension VARCHAR(5) NOT NULL,
+    position INT
+);
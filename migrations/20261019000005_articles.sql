@@ -0,0 +1,36 @@
+CREATE TABLE articles (
+    id SERIAL PRIMARY KEY,
+    domain_id INT NOT NULL,
+    name TEXT NOT NULL,
+    text TEXT,
+    seo_title TEXT,
+    seo_meta_description TEXT,
+    seo_h1 TEXT,
+    position INT NOT NULL DEFAULT 0,
+    placement TEXT NOT NULL DEFAULT 'none',
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP(0) WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE TABLE blog_articles (
+    id SERIAL PRIMARY KEY,
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    publish_date TIMESTAMP(0) WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP(0) WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE TABLE blog_article_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES blog_articles (id),
+    locale VARCHAR(255) NOT NULL,
+    name VARCHAR(255),
+    description TEXT,
+    perex TEXT
+);
+CREATE TABLE blog_article_domains (
+    id SERIAL PRIMARY KEY,
+    blog_article_id INT NOT NULL REFERENCES blog_articles (id),
+    domain_id INT NOT NULL,
+    seo_title TEXT,
+    seo_meta_description TEXT,
+    seo_h1 TEXT,
+    visible BOOLEAN NOT NULL DEFAULT FALSE
+);
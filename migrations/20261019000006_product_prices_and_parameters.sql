@@ -0,0 +1,53 @@
+CREATE TABLE pricing_groups (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    domain_id INT NOT NULL
+);
+CREATE TABLE product_calculated_prices (
+    product_id INT NOT NULL REFERENCES products (id),
+    pricing_group_id INT NOT NULL REFERENCES pricing_groups (id),
+    price_with_vat NUMERIC(20, 6),
+    PRIMARY KEY (product_id, pricing_group_id)
+);
+CREATE TABLE availabilities (
+    id SERIAL PRIMARY KEY,
+    dispatch_time INT
+);
+CREATE TABLE availability_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES availabilities (id),
+    locale VARCHAR(255) NOT NULL,
+    name VARCHAR(100) NOT NULL
+);
+ALTER TABLE products
+    ADD COLUMN calculated_availability_id INT REFERENCES availabilities (id);
+
+CREATE TABLE units (
+    id SERIAL PRIMARY KEY
+);
+CREATE TABLE unit_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES units (id),
+    locale VARCHAR(255) NOT NULL,
+    name VARCHAR(10) NOT NULL
+);
+ALTER TABLE parameters
+    ADD COLUMN unit_id INT REFERENCES units (id);
+CREATE TABLE parameter_translations (
+    id SERIAL PRIMARY KEY,
+    translatable_id INT NOT NULL REFERENCES parameters (id),
+    locale VARCHAR(255) NOT NULL,
+    name VARCHAR(100) NOT NULL
+);
+CREATE TABLE parameter_values (
+    id SERIAL PRIMARY KEY,
+    uuid UUID NOT NULL,
+    locale VARCHAR(10) NOT NULL,
+    text VARCHAR(255) NOT NULL
+);
+CREATE TABLE product_parameter_values (
+    product_id INT NOT NULL REFERENCES products (id),
+    parameter_id INT NOT NULL REFERENCES parameters (id),
+    value_id INT NOT NULL REFERENCES parameter_values (id),
+    PRIMARY KEY (product_id, parameter_id, value_id)
+);
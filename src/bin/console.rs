@@ -0,0 +1,516 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dotenvy::dotenv;
+use indicatif::HumanBytes;
+use shopsys_sqlx_test::elastic::article_index::ArticleIndex;
+use shopsys_sqlx_test::elastic::brand_index::BrandIndex;
+use shopsys_sqlx_test::elastic::bulk_sink::BulkSink;
+use shopsys_sqlx_test::elastic::category_index::CategoryIndex;
+use shopsys_sqlx_test::elastic::document_diff::{DocumentDiffReport, DocumentSelection};
+use shopsys_sqlx_test::elastic::error::ElasticError;
+use shopsys_sqlx_test::elastic::export_source::ExportSource;
+use shopsys_sqlx_test::elastic::index::Index;
+use shopsys_sqlx_test::elastic::index_definition::IndexDefinition;
+use shopsys_sqlx_test::elastic::index_definition_loader::IndexDefinitionLoader;
+use shopsys_sqlx_test::elastic::index_facade::IndexFacade;
+use shopsys_sqlx_test::elastic::index_repository::IndexRepository;
+use shopsys_sqlx_test::elastic::index_status::IndexStatusReport;
+use shopsys_sqlx_test::elastic::product_index::ProductIndex;
+use shopsys_sqlx_test::metrics::metrics;
+use shopsys_sqlx_test::postgres::postgres_config;
+use shopsys_sqlx_test::postgres::postgres_connect;
+use shopsys_sqlx_test::postgres::snapshot::ExportSnapshot;
+use shopsys_sqlx_test::settings::settings::Settings;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::process::ExitCode;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/**
+ * Replacement of the elasticsearch:* commands of php bin/console
+ *
+ * Exit code is 0 on success, 1 when any index failed and 2 on invalid arguments.
+ */
+#[derive(Parser)]
+#[command(
+    name = "console",
+    about = "Elasticsearch commands of php bin/console",
+    long_about = None
+)]
+struct Console {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(
+        name = "elasticsearch:create",
+        about = "Creates indexes and their aliases"
+    )]
+    Create(IndexArgs),
+    #[command(
+        name = "elasticsearch:migrate",
+        about = "Migrates indexes whose definition changed, missing indexes are created"
+    )]
+    Migrate(IndexArgs),
+    #[command(
+        name = "elasticsearch:export",
+        about = "Exports data into indexes, missing indexes are created"
+    )]
+    Export(ExportArgs),
+    #[command(
+        name = "elasticsearch:delete",
+        about = "Deletes indexes including their aliases"
+    )]
+    Delete(IndexArgs),
+    #[command(
+        name = "elasticsearch:list",
+        about = "Lists status of indexes and their aliases, including orphaned indexes"
+    )]
+    List(ListArgs),
+    #[command(
+        name = "elasticsearch:diff",
+        about = "Compares exported documents with the documents in the current index, exits with 1 on differences"
+    )]
+    Diff(DiffArgs),
+}
+
+#[derive(Args)]
+struct IndexArgs {
+    #[arg(long, help = "Domain id, all domains when omitted")]
+    domain: Option<u8>,
+    #[arg(long, help = "Index name, e.g. product, all indexes when omitted")]
+    index: Option<String>,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    #[command(flatten)]
+    index_args: IndexArgs,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+#[derive(Clone, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[command(flatten)]
+    index_args: IndexArgs,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires = "index",
+        help = "Export only these ids of the --index, e.g. --ids 1,2,3"
+    )]
+    ids: Vec<i32>,
+    #[arg(long, help = "All batches read the same database snapshot")]
+    snapshot: bool,
+    #[arg(
+        long,
+        help = "Check documents against the mapping of the definition before they are sent"
+    )]
+    strict_mapping: bool,
+    #[arg(
+        long,
+        help = "Write Prometheus metrics of the export to this file, e.g. for the node_exporter textfile collector"
+    )]
+    metrics_file: Option<String>,
+    #[arg(
+        long,
+        help = "Write the _bulk requests as NDJSON to this file ('-' for stdout) instead of sending them to Elasticsearch"
+    )]
+    output: Option<String>,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    #[command(flatten)]
+    index_args: IndexArgs,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires = "index",
+        help = "Compare only these ids of the --index, e.g. --ids 1,2,3"
+    )]
+    ids: Vec<i32>,
+    #[arg(
+        long,
+        conflicts_with = "ids",
+        help = "Compare this number of random documents of the index, all documents when omitted"
+    )]
+    sample: Option<u32>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
+/**
+ * Argument accepted by clap but not matching the settings, e.g. --domain that is not configured
+ */
+#[derive(Debug)]
+struct InvalidArgument(String);
+
+impl Error for InvalidArgument {}
+
+impl Display for InvalidArgument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv().ok();
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "warn,shopsys_sqlx_test=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    let console = Console::parse();
+
+    match run(console.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            match err.is::<InvalidArgument>() {
+                true => ExitCode::from(2),
+                false => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+async fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    let settings = Settings::load()?;
+    let index_repository = IndexRepository::from_settings(&settings.elasticsearch)?;
+    let index_facade = IndexFacade::new(&index_repository).with_export_settings(&settings.export);
+    let index_definition_loader = IndexDefinitionLoader::from_settings(&settings.elasticsearch);
+
+    match command {
+        Command::Create(args) => {
+            for index_definition in
+                get_index_definitions(&settings, &index_definition_loader, &args)?
+            {
+                index_facade.create(&index_definition).await?;
+            }
+        }
+        Command::Migrate(args) => {
+            for index_definition in
+                get_index_definitions(&settings, &index_definition_loader, &args)?
+            {
+                index_facade.migrate(&index_definition).await?;
+            }
+        }
+        Command::Export(args) => {
+            // dry-run export, Elasticsearch is not called at all
+            let index_repository = match &args.output {
+                Some(output) => index_repository
+                    .clone()
+                    .with_bulk_sink(BulkSink::from_output(output)?),
+                None => index_repository.clone(),
+            };
+            let mut export_settings = settings.export.clone();
+            export_settings.strict_mapping |= args.strict_mapping;
+            let index_facade =
+                IndexFacade::new(&index_repository).with_export_settings(&export_settings);
+            let index_definitions =
+                get_index_definitions(&settings, &index_definition_loader, &args.index_args)?;
+            let result = export(&settings, &index_facade, &index_definitions, &args).await;
+            // written also after a failure, so failed bulk requests get to the metrics
+            if let Some(metrics_file) = &args.metrics_file {
+                std::fs::write(metrics_file, metrics::render())?;
+            }
+            result?;
+        }
+        Command::Delete(args) => {
+            for index_definition in
+                get_index_definitions(&settings, &index_definition_loader, &args)?
+            {
+                index_facade.delete(&index_definition).await?;
+            }
+        }
+        Command::Diff(args) => {
+            let index_definitions =
+                get_index_definitions(&settings, &index_definition_loader, &args.index_args)?;
+            diff(&settings, &index_facade, &index_definitions, &args).await?;
+        }
+        Command::List(args) => {
+            let index_definitions =
+                get_index_definitions(&settings, &index_definition_loader, &args.index_args)?;
+            let report = index_facade.get_status_report(&index_definitions).await?;
+            match args.format {
+                OutputFormat::Table => print_status_table(&report),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn diff(
+    settings: &Settings,
+    index_facade: &IndexFacade<'_>,
+    index_definitions: &[IndexDefinition],
+    args: &DiffArgs,
+) -> Result<(), Box<dyn Error>> {
+    settings.validate_database()?;
+    let pools = postgres_connect::get_pools(
+        &settings.get_postgres_config(postgres_config::EXPORTER_PROFILE),
+    )
+    .await?;
+    // documents in the index were exported from the primary, replica may not have them yet
+    let pool = pools.primary();
+
+    let document_selection = match (&args.sample, args.ids.is_empty()) {
+        (Some(size), _) => DocumentSelection::Sample(*size),
+        (None, false) => DocumentSelection::Ids(args.ids.clone()),
+        (None, true) => DocumentSelection::All,
+    };
+
+    let export_source = ExportSource::new(pool.clone(), settings.domains.clone());
+    let mut has_differences = false;
+    for index_definition in index_definitions {
+        let report = match index_definition.get_index_name() {
+            name if name == ProductIndex::name() => {
+                let index = ProductIndex::new(export_source.clone());
+                index_facade
+                    .diff(&index, index_definition, &document_selection)
+                    .await?
+            }
+            name if name == CategoryIndex::name() => {
+                let index = CategoryIndex::new(export_source.clone());
+                index_facade
+                    .diff(&index, index_definition, &document_selection)
+                    .await?
+            }
+            name if name == BrandIndex::name() => {
+                let index = BrandIndex::new(export_source.clone());
+                index_facade
+                    .diff(&index, index_definition, &document_selection)
+                    .await?
+            }
+            name if name == ArticleIndex::name() => {
+                let index = ArticleIndex::new(export_source.clone());
+                index_facade
+                    .diff(&index, index_definition, &document_selection)
+                    .await?
+            }
+            name => return Err(format!("Index '{name}' has no export implemented").into()),
+        };
+
+        match args.format {
+            OutputFormat::Table => print_diff_table(index_definition, &report),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+        has_differences |= report.has_differences();
+    }
+
+    match has_differences {
+        true => Err("Exported documents differ from the documents in the index".into()),
+        false => Ok(()),
+    }
+}
+
+async fn export(
+    settings: &Settings,
+    index_facade: &IndexFacade<'_>,
+    index_definitions: &[IndexDefinition],
+    args: &ExportArgs,
+) -> Result<(), Box<dyn Error>> {
+    settings.validate_database()?;
+    let pools = postgres_connect::get_pools(
+        &settings.get_postgres_config(postgres_config::EXPORTER_PROFILE),
+    )
+    .await?;
+
+    // ids are exported right after they were changed, replica may not have them yet
+    let pool = match args.ids.is_empty() {
+        true => pools.read(),
+        false => pools.primary(),
+    };
+    if args.ids.is_empty() {
+        pools.warn_if_replica_is_lagging().await;
+    }
+
+    let snapshot = match args.snapshot {
+        true => Some(ExportSnapshot::begin(pool).await?),
+        false => None,
+    };
+
+    let mut export_source = ExportSource::new(pool.clone(), settings.domains.clone());
+    if let Some(snapshot) = &snapshot {
+        export_source = export_source.with_snapshot(snapshot.id());
+    }
+    let product_index = ProductIndex::new(export_source.clone());
+    let category_index = CategoryIndex::new(export_source.clone());
+    let brand_index = BrandIndex::new(export_source.clone());
+    let article_index = ArticleIndex::new(export_source);
+
+    for index_definition in index_definitions {
+        match index_definition.get_index_name() {
+            name if name == ProductIndex::name() => {
+                export_index(index_facade, &product_index, index_definition, &args.ids).await?
+            }
+            name if name == CategoryIndex::name() => {
+                export_index(index_facade, &category_index, index_definition, &args.ids).await?
+            }
+            name if name == BrandIndex::name() => {
+                export_index(index_facade, &brand_index, index_definition, &args.ids).await?
+            }
+            name if name == ArticleIndex::name() => {
+                export_index(index_facade, &article_index, index_definition, &args.ids).await?
+            }
+            name => return Err(format!("Index '{name}' has no export implemented").into()),
+        }
+    }
+
+    if let Some(snapshot) = snapshot {
+        snapshot.release().await?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Whole index, or only the given ids when --ids is set
+ */
+async fn export_index<I: Index>(
+    index_facade: &IndexFacade<'_>,
+    index: &I,
+    index_definition: &IndexDefinition,
+    ids: &[i32],
+) -> Result<(), ElasticError> {
+    match ids.is_empty() {
+        true => index_facade.export(index, index_definition).await,
+        false => index_facade.export_ids(index, index_definition, ids).await,
+    }
+}
+
+fn print_status_table(report: &IndexStatusReport) {
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+    println!(
+        "{:<12} {:<6} {:<30} {:<50} {:<10} {:<7} {:>10} {:>10}",
+        "index", "domain", "alias", "index name", "state", "health", "docs", "size"
+    );
+    for status in &report.indexes {
+        let state = match (&status.index, status.is_up_to_date) {
+            (None, _) => "missing",
+            (Some(_), true) => "up to date",
+            (Some(_), false) => "outdated",
+        };
+        println!(
+            "{:<12} {:<6} {:<30} {:<50} {:<10} {:<7} {:>10} {:>10}",
+            status.index_name,
+            status.domain_id,
+            status.alias,
+            or_dash(status.index.clone()),
+            state,
+            or_dash(status.health.clone()),
+            or_dash(status.docs_count.map(|count| count.to_string())),
+            or_dash(
+                status
+                    .store_size_bytes
+                    .map(|size| HumanBytes(size).to_string())
+            ),
+        );
+    }
+
+    if !report.orphaned_indexes.is_empty() {
+        println!("\nOrphaned indexes (not behind any alias):");
+        for index_info in &report.orphaned_indexes {
+            println!(
+                "  {:<50} {:>10} {:>10}",
+                index_info.index,
+                or_dash(index_info.docs_count.map(|count| count.to_string())),
+                or_dash(
+                    index_info
+                        .store_size_bytes
+                        .map(|size| HumanBytes(size).to_string())
+                ),
+            );
+        }
+    }
+}
+
+fn print_diff_table(index_definition: &IndexDefinition, report: &DocumentDiffReport) {
+    println!(
+        "Index '{}' on domain '{}': {} documents compared, {} identical, {} missing in index, {} missing in export",
+        index_definition.get_index_name(),
+        index_definition.get_domain_id(),
+        report.compared_count,
+        report.identical_count,
+        report.documents_missing_in_index,
+        report.documents_missing_in_export
+    );
+    if !report.missing_document_example_ids.is_empty() {
+        println!(
+            "Missing documents, e.g.: {}",
+            join_ids(&report.missing_document_example_ids)
+        );
+    }
+    if report.fields.is_empty() {
+        return;
+    }
+
+    println!();
+    println!(
+        "{:<40} {:>10} {:>10} {:>10} {:>10}  example ids",
+        "field", "not in idx", "not in exp", "type", "value"
+    );
+    for (field, difference) in &report.fields {
+        println!(
+            "{:<40} {:>10} {:>10} {:>10} {:>10}  {}",
+            field,
+            difference.missing_in_index,
+            difference.missing_in_export,
+            difference.type_mismatches,
+            difference.value_differences,
+            join_ids(&difference.example_ids)
+        );
+    }
+    println!();
+}
+
+fn join_ids(ids: &[i32]) -> String {
+    ids.iter()
+        .map(i32::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/**
+ * Definitions for all combinations of the requested indexes and domains
+ */
+fn get_index_definitions(
+    settings: &Settings,
+    index_definition_loader: &IndexDefinitionLoader,
+    args: &IndexArgs,
+) -> Result<Vec<IndexDefinition>, Box<dyn Error>> {
+    let mut domain_ids = settings.get_domain_ids();
+    if let Some(domain_id) = args.domain {
+        if !domain_ids.contains(&domain_id) {
+            return Err(InvalidArgument(format!("Domain '{domain_id}' is not configured")).into());
+        }
+        domain_ids = vec![domain_id];
+    }
+
+    let mut index_definitions = index_definition_loader.get_definitions(&domain_ids)?;
+    if let Some(index_name) = &args.index {
+        index_definitions
+            .retain(|index_definition| index_definition.get_index_name() == index_name);
+        if index_definitions.is_empty() {
+            return Err(InvalidArgument(format!("Index '{index_name}' has no definition")).into());
+        }
+    }
+
+    Ok(index_definitions)
+}
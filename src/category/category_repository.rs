@@ -0,0 +1,23 @@
+use sqlx::{Pool, Postgres};
+
+/**
+ * Name of the category visible on the domain, None for an unknown or hidden category
+ */
+pub async fn find_category_name(
+    pool: &Pool<Postgres>,
+    category_id: i32,
+    domain_id: u8,
+    locale: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT ct.name
+        FROM category_translations ct
+        INNER JOIN category_domains cd ON cd.category_id = ct.translatable_id
+        WHERE ct.translatable_id = $1 AND ct.locale = $2 AND cd.domain_id = $3 AND cd.visible = TRUE"#,
+        category_id,
+        locale,
+        domain_id as i32
+    )
+    .fetch_optional(pool)
+    .await
+}
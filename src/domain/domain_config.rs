@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+/**
+ * Shopsys DomainConfig - one domain from config/domains.yaml, here the [[domains]] of config/settings.toml
+ */
+#[derive(Debug, Clone, Deserialize)]
+pub struct DomainConfig {
+    pub id: u8,
+    pub name: String,
+    pub locale: String,
+    /// default pricing group of the domain (Shopsys setting defaultPricingGroupId), products are exported for it
+    pub pricing_group_id: i32,
+}
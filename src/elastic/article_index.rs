@@ -0,0 +1,240 @@
+use crate::elastic::error::ElasticError;
+use crate::elastic::export_source::ExportSource;
+use crate::elastic::index::Index;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::BTreeMap;
+
+pub const ARTICLE_TYPE: &str = "article";
+pub const BLOG_ARTICLE_TYPE: &str = "blog_article";
+
+/**
+ * Routes of the article and blog article detail, under which their friendly_urls are stored
+ */
+const ARTICLE_ROUTE_NAME: &str = "front_article_detail";
+const BLOG_ARTICLE_ROUTE_NAME: &str = "front_blogarticle_detail";
+
+#[derive(Debug, Serialize)]
+pub struct ArticleExportData {
+    id: i32,
+    #[serde(rename = "type")]
+    article_type: String,
+    title: String,
+    perex: Option<String>,
+    text: String,
+    slug: String,
+    /** friendly url, there is no route with the article id */
+    url: Option<String>,
+    published_at: String,
+    domain_id: u8,
+    visible: bool,
+}
+
+/**
+ * Articles (CMS pages) and blog articles of the domain in one index, so site search returns both at once
+ *
+ * Both tables have their own id sequence, so documents get their own ids: id * 2 for articles
+ * and id * 2 + 1 for blog articles. The original id is exported in "id" together with "type",
+ * and --ids of the export are these document ids. Document ids are computed as BIGINT,
+ * ids of articles above i32::MAX / 2 fail the export instead of overflowing.
+ */
+#[derive(Clone)]
+pub struct ArticleIndex {
+    export_source: ExportSource,
+}
+
+#[derive(Debug)]
+struct ArticleForElasticExport {
+    document_id: i64,
+    id: i32,
+    article_type: String,
+    title: Option<String>,
+    perex: Option<String>,
+    text: Option<String>,
+    slug: Option<String>,
+    published_at: String,
+    visible: bool,
+}
+
+impl ArticleIndex {
+    pub fn new(export_source: ExportSource) -> Self {
+        Self { export_source }
+    }
+
+    /**
+     * Documents after last_processed_id, restricted to document ids when set, limit None means all of them
+     *
+     * Articles are published when created, blog articles on their publish_date.
+     */
+    #[tracing::instrument(name = "articles_query", level = "debug", skip(self, conn))]
+    async fn get_articles(
+        &self,
+        conn: &mut PgConnection,
+        domain_id: u8,
+        last_processed_id: u32,
+        document_ids: Option<Vec<i32>>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ArticleForElasticExport>, ElasticError> {
+        let locale = self.export_source.get_locale(domain_id)?;
+        sqlx::query_as!(
+            ArticleForElasticExport,
+            r#"
+            SELECT document_id AS "document_id!", id AS "id!", article_type AS "article_type!",
+                title, perex, text, slug, published_at AS "published_at!", visible AS "visible!"
+            FROM (
+                SELECT a.id::BIGINT * 2 AS document_id, a.id, $3 AS article_type, a.name AS title,
+                    NULL::TEXT AS perex, a.text, fu.slug,
+                    to_char(a.created_at, 'YYYY-MM-DD"T"HH24:MI:SS') AS published_at,
+                    NOT a.hidden AS visible
+                FROM articles a
+                LEFT JOIN friendly_urls fu ON fu.entity_id = a.id AND fu.domain_id = a.domain_id
+                    AND fu.route_name = $4 AND fu.main = TRUE
+                WHERE a.domain_id = $1
+                UNION ALL
+                SELECT ba.id::BIGINT * 2 + 1, ba.id, $5, bat.name, bat.perex, bat.description, fu.slug,
+                    to_char(ba.publish_date, 'YYYY-MM-DD"T"HH24:MI:SS'),
+                    bad.visible AND NOT ba.hidden AND ba.publish_date <= NOW()
+                FROM blog_articles ba
+                INNER JOIN blog_article_domains bad ON bad.blog_article_id = ba.id AND bad.domain_id = $1
+                LEFT JOIN blog_article_translations bat ON bat.translatable_id = ba.id AND bat.locale = $2
+                LEFT JOIN friendly_urls fu ON fu.entity_id = ba.id AND fu.domain_id = bad.domain_id
+                    AND fu.route_name = $6 AND fu.main = TRUE
+            ) documents
+            WHERE document_id > $7 AND ($8::int[] IS NULL OR document_id = ANY ($8))
+            ORDER BY document_id
+            LIMIT $9
+            "#,
+            domain_id as i32,
+            locale,
+            ARTICLE_TYPE,
+            ARTICLE_ROUTE_NAME,
+            BLOG_ARTICLE_TYPE,
+            BLOG_ARTICLE_ROUTE_NAME,
+            last_processed_id as i64,
+            document_ids.as_deref(),
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(ElasticError::Postgres)
+    }
+
+    fn get_export_data(
+        articles: Vec<ArticleForElasticExport>,
+        domain_id: u8,
+    ) -> Result<BTreeMap<i32, ArticleExportData>, ElasticError> {
+        articles
+            .into_iter()
+            .map(|article| {
+                let document_id = i32::try_from(article.document_id)
+                    .map_err(|_| ElasticError::DocumentIdOutOfRange(article.document_id))?;
+                let slug = article.slug.unwrap_or_default();
+                Ok((
+                    document_id,
+                    ArticleExportData {
+                        id: article.id,
+                        article_type: article.article_type,
+                        title: article.title.unwrap_or_default(),
+                        perex: article.perex.as_deref().map(strip_html_tags),
+                        text: strip_html_tags(&article.text.unwrap_or_default()),
+                        url: (!slug.is_empty()).then(|| format!("/{slug}/")),
+                        slug,
+                        published_at: article.published_at,
+                        domain_id,
+                        visible: article.visible,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+/**
+ * Text of the WYSIWYG content for full-text search, tags are replaced by spaces and whitespace is collapsed
+ */
+fn strip_html_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut is_in_tag = false;
+    for character in html.chars() {
+        match character {
+            '<' => is_in_tag = true,
+            '>' if is_in_tag => {
+                is_in_tag = false;
+                text.push(' ');
+            }
+            _ if !is_in_tag => text.push(character),
+            _ => {}
+        }
+    }
+
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+impl Index for ArticleIndex {
+    type Document = ArticleExportData;
+
+    fn name() -> &'static str {
+        "article"
+    }
+
+    async fn get_total_count(&self, domain_id: u8) -> Result<i64, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let total_count = sqlx::query_scalar!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM articles WHERE domain_id = $1)
+                + (SELECT COUNT(*) FROM blog_article_domains WHERE domain_id = $1)
+                AS "total_count!"
+            "#,
+            domain_id as i32
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(ElasticError::Postgres)?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(total_count)
+    }
+
+    async fn get_export_data_for_batch(
+        &self,
+        domain_id: u8,
+        last_processed_id: u32,
+        batch_size: u32,
+    ) -> Result<BTreeMap<i32, ArticleExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let articles = self
+            .get_articles(
+                &mut transaction,
+                domain_id,
+                last_processed_id,
+                None,
+                Some(batch_size as i64),
+            )
+            .await?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Self::get_export_data(articles, domain_id)
+    }
+
+    async fn get_export_data_for_ids(
+        &self,
+        domain_id: u8,
+        ids: Vec<i32>,
+    ) -> Result<BTreeMap<i32, ArticleExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let articles = self
+            .get_articles(&mut transaction, domain_id, 0, Some(ids), None)
+            .await?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Self::get_export_data(articles, domain_id)
+    }
+}
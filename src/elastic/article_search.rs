@@ -0,0 +1,71 @@
+use super::error::ElasticError;
+use super::index_definition::IndexDefinition;
+use super::index_repository::IndexRepository;
+use super::product_search::get_sources;
+use serde_json::{json, Value};
+
+pub const ARTICLE_SEARCH_LIMIT: u32 = 10;
+pub const ARTICLE_SEARCH_MAX_LIMIT: u32 = 100;
+
+/**
+ * Title weighs the most, the text of long articles would outscore a matching title otherwise
+ */
+const ARTICLE_SEARCH_FIELDS: [&str; 6] = [
+    "title^5",
+    "title.edge_ngram_with_diacritic^3",
+    "title.edge_ngram_without_diacritic^2",
+    "perex^2",
+    "text",
+    "text.full_without_diacritic",
+];
+
+/**
+ * Site search in articles and blog articles of the article index
+ */
+pub struct ArticleSearch<'a> {
+    index_repository: &'a IndexRepository,
+}
+
+impl<'a> ArticleSearch<'a> {
+    pub fn new(index_repository: &'a IndexRepository) -> Self {
+        ArticleSearch { index_repository }
+    }
+
+    /**
+     * Visible articles by the title, perex and text, returns "_source" of the documents without the text
+     */
+    pub async fn search(
+        &self,
+        index_definition: &IndexDefinition,
+        text: &str,
+        limit: u32,
+    ) -> Result<Vec<Value>, ElasticError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let body = json!({
+            "query": {
+                "bool": {
+                    "must": {
+                        "multi_match": {
+                            "query": text,
+                            "fields": ARTICLE_SEARCH_FIELDS,
+                        }
+                    },
+                    "filter": [{ "term": { "visible": true } }],
+                }
+            },
+            "_source": ["id", "type", "title", "perex", "slug", "url", "published_at"],
+            "size": limit,
+            "track_total_hits": false,
+        });
+        let response_body = self
+            .index_repository
+            .search(&index_definition.get_index_alias(), body)
+            .await?;
+
+        Ok(get_sources(&response_body).cloned().collect())
+    }
+}
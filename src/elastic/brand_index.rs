@@ -0,0 +1,186 @@
+use crate::elastic::error::ElasticError;
+use crate::elastic::export_source::ExportSource;
+use crate::elastic::index::Index;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::BTreeMap;
+
+/**
+ * Route of the brand detail, under which friendly_urls of brands are stored
+ */
+const BRAND_ROUTE_NAME: &str = "front_brand_detail";
+
+#[derive(Debug, Serialize)]
+pub struct BrandExportData {
+    id: i32,
+    name: String,
+    slug: String,
+    /** friendly url, there is no route with the brand id */
+    url: Option<String>,
+    description: Option<String>,
+    image_url: Option<String>,
+    product_count: i64,
+}
+
+/**
+ * Shopsys BrandIndex - all brands, with the number of products visible on the domain
+ *
+ * Brands without visible products are exported as well, listings filter them out by "product_count".
+ */
+#[derive(Clone)]
+pub struct BrandIndex {
+    export_source: ExportSource,
+}
+
+#[derive(Debug)]
+struct BrandForElasticExport {
+    id: i32,
+    name: String,
+    slug: Option<String>,
+    description: Option<String>,
+    image_id: Option<i32>,
+    image_extension: Option<String>,
+    product_count: i64,
+}
+
+impl BrandIndex {
+    pub fn new(export_source: ExportSource) -> Self {
+        Self { export_source }
+    }
+
+    /**
+     * Brands after last_processed_id, restricted to ids when set, limit None means all of them
+     *
+     * The image is the first one of the brand by position, as ImageFacade::getImageByEntity() takes it.
+     */
+    #[tracing::instrument(name = "brands_query", level = "debug", skip(self, conn))]
+    async fn get_brands(
+        &self,
+        conn: &mut PgConnection,
+        domain_id: u8,
+        last_processed_id: u32,
+        ids: Option<Vec<i32>>,
+        limit: Option<i64>,
+    ) -> Result<Vec<BrandForElasticExport>, ElasticError> {
+        let locale = self.export_source.get_locale(domain_id)?;
+        sqlx::query_as!(
+            BrandForElasticExport,
+            r#"
+            SELECT b.id, b.name, fu.slug AS "slug?", bt.description AS "description?",
+                i.id AS "image_id?", i.extension AS "image_extension?",
+                (
+                    SELECT COUNT(*)
+                    FROM products p
+                    WHERE p.brand_id = b.id
+                    AND EXISTS (
+                        SELECT 1 FROM product_visibilities pv
+                        WHERE pv.product_id = p.id AND pv.domain_id = $1 AND pv.visible = TRUE
+                    )
+                ) AS "product_count!"
+            FROM brands b
+            LEFT JOIN brand_translations bt ON bt.translatable_id = b.id AND bt.locale = $2
+            LEFT JOIN friendly_urls fu ON fu.entity_id = b.id AND fu.domain_id = $1
+                AND fu.route_name = $3 AND fu.main = TRUE
+            LEFT JOIN LATERAL (
+                SELECT id, extension FROM images
+                WHERE entity_name = 'brand' AND entity_id = b.id
+                ORDER BY position NULLS LAST, id
+                LIMIT 1
+            ) i ON TRUE
+            WHERE b.id > $4 AND ($5::int[] IS NULL OR b.id = ANY ($5))
+            ORDER BY b.id
+            LIMIT $6
+            "#,
+            domain_id as i32,
+            locale,
+            BRAND_ROUTE_NAME,
+            last_processed_id as i32,
+            ids.as_deref(),
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(ElasticError::Postgres)
+    }
+
+    fn get_export_data(brands: Vec<BrandForElasticExport>) -> BTreeMap<i32, BrandExportData> {
+        brands
+            .into_iter()
+            .map(|brand| {
+                let image_url = match (brand.image_id, brand.image_extension) {
+                    (Some(image_id), Some(extension)) => Some(format!(
+                        "/content/images/brand/original/{image_id}.{extension}"
+                    )),
+                    _ => None,
+                };
+                let slug = brand.slug.unwrap_or_default();
+                (
+                    brand.id,
+                    BrandExportData {
+                        id: brand.id,
+                        name: brand.name,
+                        url: (!slug.is_empty()).then(|| format!("/{slug}/")),
+                        slug,
+                        description: brand.description,
+                        image_url,
+                        product_count: brand.product_count,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl Index for BrandIndex {
+    type Document = BrandExportData;
+
+    fn name() -> &'static str {
+        "brand"
+    }
+
+    async fn get_total_count(&self, _domain_id: u8) -> Result<i64, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let total_count = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "total_count!" FROM brands"#)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(ElasticError::Postgres)?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(total_count)
+    }
+
+    async fn get_export_data_for_batch(
+        &self,
+        domain_id: u8,
+        last_processed_id: u32,
+        batch_size: u32,
+    ) -> Result<BTreeMap<i32, BrandExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let brands = self
+            .get_brands(
+                &mut transaction,
+                domain_id,
+                last_processed_id,
+                None,
+                Some(batch_size as i64),
+            )
+            .await?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(Self::get_export_data(brands))
+    }
+
+    async fn get_export_data_for_ids(
+        &self,
+        domain_id: u8,
+        ids: Vec<i32>,
+    ) -> Result<BTreeMap<i32, BrandExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let brands = self
+            .get_brands(&mut transaction, domain_id, 0, Some(ids), None)
+            .await?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(Self::get_export_data(brands))
+    }
+}
@@ -0,0 +1,98 @@
+use super::error::ElasticError;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+/**
+ * Output of IndexRepository::bulk_update and delete_ids
+ *
+ * File and stdout get the body of the _bulk request as NDJSON, with "_index" set to the alias in each action,
+ * so the file can be sent later by: curl -H "Content-Type: application/x-ndjson" -XPOST localhost:9200/_bulk --data-binary @file.ndjson
+ */
+#[derive(Clone, Default)]
+pub enum BulkSink {
+    #[default]
+    Elasticsearch,
+    File(Arc<Mutex<BufWriter<File>>>),
+    Stdout,
+}
+
+impl BulkSink {
+    /**
+     * "-" is stdout, anything else is a path of the file, which is truncated
+     */
+    pub fn from_output(output: &str) -> Result<Self, ElasticError> {
+        match output {
+            "-" => Ok(BulkSink::Stdout),
+            path => File::create(path)
+                .map(|file| BulkSink::File(Arc::new(Mutex::new(BufWriter::new(file)))))
+                .map_err(|err| ElasticError::Output(format!("{path}: {err}"))),
+        }
+    }
+
+    pub fn is_elasticsearch(&self) -> bool {
+        matches!(self, BulkSink::Elasticsearch)
+    }
+
+    pub fn write_updates<D: Serialize>(
+        &self,
+        index_alias: &str,
+        current_batch_data: &BTreeMap<i32, D>,
+    ) -> Result<(), ElasticError> {
+        let mut ndjson = String::new();
+        for (id, data) in current_batch_data {
+            push_line(
+                &mut ndjson,
+                json!({ "update": { "_index": index_alias, "_id": id.to_string() } }),
+            );
+            push_line(&mut ndjson, json!({ "doc": data, "doc_as_upsert": true }));
+        }
+
+        self.write(&ndjson)
+    }
+
+    pub fn write_deletes(&self, index_alias: &str, ids: &[i32]) -> Result<(), ElasticError> {
+        let mut ndjson = String::new();
+        for id in ids {
+            push_line(
+                &mut ndjson,
+                json!({ "delete": { "_index": index_alias, "_id": id.to_string() } }),
+            );
+        }
+
+        self.write(&ndjson)
+    }
+
+    /**
+     * Whole batch is written at once, so lines of batches exported at once are not mixed
+     */
+    fn write(&self, ndjson: &str) -> Result<(), ElasticError> {
+        let result = match self {
+            BulkSink::Elasticsearch => {
+                unreachable!("Bulk requests to Elasticsearch are sent by IndexRepository")
+            }
+            BulkSink::File(writer) => {
+                let mut writer = writer.lock().unwrap();
+                writer
+                    .write_all(ndjson.as_bytes())
+                    .and_then(|_| writer.flush())
+            }
+            BulkSink::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                stdout
+                    .write_all(ndjson.as_bytes())
+                    .and_then(|_| stdout.flush())
+            }
+        };
+
+        result.map_err(|err| ElasticError::Output(err.to_string()))
+    }
+}
+
+fn push_line(ndjson: &mut String, line: serde_json::Value) {
+    ndjson.push_str(&line.to_string());
+    ndjson.push('\n');
+}
@@ -0,0 +1,205 @@
+use crate::elastic::error::ElasticError;
+use crate::elastic::export_source::ExportSource;
+use crate::elastic::index::Index;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::BTreeMap;
+
+/**
+ * Route of the category detail, under which friendly_urls of categories are stored
+ */
+const CATEGORY_ROUTE_NAME: &str = "front_product_list";
+
+#[derive(Debug, Serialize)]
+pub struct CategoryExportData {
+    id: i32,
+    name: String,
+    parent_id: Option<i32>,
+    level: i32,
+    lft: i32,
+    rgt: i32,
+    visible: bool,
+    slug: String,
+    url: String,
+    product_count: i64,
+    seo_title: Option<String>,
+    seo_h1: Option<String>,
+    seo_meta_description: Option<String>,
+    description: Option<String>,
+}
+
+/**
+ * Shopsys CategoryIndex - categories of the domain without the root category
+ *
+ * Invisible categories are exported as well with "visible": false,
+ * so the menu and the search filter them out and a hidden category does not need a reindex of its subtree.
+ */
+#[derive(Clone)]
+pub struct CategoryIndex {
+    export_source: ExportSource,
+}
+
+#[derive(Debug)]
+struct CategoryForElasticExport {
+    id: i32,
+    parent_id: Option<i32>,
+    level: i32,
+    lft: i32,
+    rgt: i32,
+    visible: bool,
+    name: Option<String>,
+    slug: Option<String>,
+    product_count: i64,
+    seo_title: Option<String>,
+    seo_h1: Option<String>,
+    seo_meta_description: Option<String>,
+    description: Option<String>,
+}
+
+impl CategoryIndex {
+    pub fn new(export_source: ExportSource) -> Self {
+        Self { export_source }
+    }
+
+    /**
+     * Categories after last_processed_id, restricted to ids when set, limit None means all of them
+     *
+     * Product count is the number of products visible on the domain that are assigned directly to the category.
+     */
+    #[tracing::instrument(name = "categories_query", level = "debug", skip(self, conn))]
+    async fn get_categories(
+        &self,
+        conn: &mut PgConnection,
+        domain_id: u8,
+        last_processed_id: u32,
+        ids: Option<Vec<i32>>,
+        limit: Option<i64>,
+    ) -> Result<Vec<CategoryForElasticExport>, ElasticError> {
+        let locale = self.export_source.get_locale(domain_id)?;
+        sqlx::query_as!(
+            CategoryForElasticExport,
+            r#"
+            SELECT c.id, c.parent_id, c.level, c.lft, c.rgt, cd.visible,
+                ct.name AS "name?", fu.slug AS "slug?",
+                cd.seo_title, cd.seo_h1, cd.seo_meta_description, cd.description,
+                (
+                    SELECT COUNT(*)
+                    FROM product_category_domains pcd
+                    WHERE pcd.category_id = c.id AND pcd.domain_id = cd.domain_id
+                    AND EXISTS (
+                        SELECT 1 FROM product_visibilities pv
+                        WHERE pv.product_id = pcd.product_id AND pv.domain_id = pcd.domain_id AND pv.visible = TRUE
+                    )
+                ) AS "product_count!"
+            FROM categories c
+            INNER JOIN category_domains cd ON cd.category_id = c.id AND cd.domain_id = $1
+            LEFT JOIN category_translations ct ON ct.translatable_id = c.id AND ct.locale = $2
+            LEFT JOIN friendly_urls fu ON fu.entity_id = c.id AND fu.domain_id = cd.domain_id
+                AND fu.route_name = $3 AND fu.main = TRUE
+            WHERE c.parent_id IS NOT NULL AND c.id > $4 AND ($5::int[] IS NULL OR c.id = ANY ($5))
+            ORDER BY c.id
+            LIMIT $6
+            "#,
+            domain_id as i32,
+            locale,
+            CATEGORY_ROUTE_NAME,
+            last_processed_id as i32,
+            ids.as_deref(),
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(ElasticError::Postgres)
+    }
+
+    fn get_export_data(
+        categories: Vec<CategoryForElasticExport>,
+    ) -> BTreeMap<i32, CategoryExportData> {
+        categories
+            .into_iter()
+            .map(|category| {
+                (
+                    category.id,
+                    CategoryExportData {
+                        id: category.id,
+                        name: category.name.unwrap_or_default(),
+                        parent_id: category.parent_id,
+                        level: category.level,
+                        lft: category.lft,
+                        rgt: category.rgt,
+                        visible: category.visible,
+                        slug: category.slug.unwrap_or_default(),
+                        url: format!("/category/{}", category.id),
+                        product_count: category.product_count,
+                        seo_title: category.seo_title,
+                        seo_h1: category.seo_h1,
+                        seo_meta_description: category.seo_meta_description,
+                        description: category.description,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl Index for CategoryIndex {
+    type Document = CategoryExportData;
+
+    fn name() -> &'static str {
+        "category"
+    }
+
+    async fn get_total_count(&self, domain_id: u8) -> Result<i64, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let total_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "total_count!"
+            FROM categories c
+            INNER JOIN category_domains cd ON cd.category_id = c.id
+            WHERE cd.domain_id = $1 AND c.parent_id IS NOT NULL
+            "#,
+            domain_id as i32
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(ElasticError::Postgres)?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(total_count)
+    }
+
+    async fn get_export_data_for_batch(
+        &self,
+        domain_id: u8,
+        last_processed_id: u32,
+        batch_size: u32,
+    ) -> Result<BTreeMap<i32, CategoryExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let categories = self
+            .get_categories(
+                &mut transaction,
+                domain_id,
+                last_processed_id,
+                None,
+                Some(batch_size as i64),
+            )
+            .await?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(Self::get_export_data(categories))
+    }
+
+    async fn get_export_data_for_ids(
+        &self,
+        domain_id: u8,
+        ids: Vec<i32>,
+    ) -> Result<BTreeMap<i32, CategoryExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let categories = self
+            .get_categories(&mut transaction, domain_id, 0, Some(ids), None)
+            .await?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(Self::get_export_data(categories))
+    }
+}
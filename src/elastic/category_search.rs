@@ -0,0 +1,158 @@
+use super::error::ElasticError;
+use super::index_definition::IndexDefinition;
+use super::index_repository::IndexRepository;
+use super::product_search::get_sources;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+pub const CATEGORY_SEARCH_LIMIT: u32 = 20;
+pub const CATEGORY_SEARCH_MAX_LIMIT: u32 = 100;
+
+/**
+ * Default max_result_window, the whole category tree of a domain is loaded at once
+ */
+const CATEGORY_MENU_LIMIT: u32 = 10000;
+
+/**
+ * Stemmed category name and its edge-ngram subfields, searched with their "full_*" search analyzers
+ */
+const CATEGORY_SEARCH_FIELDS: [&str; 3] = [
+    "name^3",
+    "name.edge_ngram_with_diacritic^2",
+    "name.edge_ngram_without_diacritic",
+];
+
+#[derive(Debug, Serialize)]
+pub struct CategoryMenuItem {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub url: String,
+    pub level: i64,
+    pub product_count: i64,
+    pub children: Vec<CategoryMenuItem>,
+}
+
+impl CategoryMenuItem {
+    fn from_source(source: &Value) -> Self {
+        CategoryMenuItem {
+            id: source["id"].as_i64().unwrap_or_default(),
+            name: source["name"].as_str().unwrap_or_default().to_string(),
+            slug: source["slug"].as_str().unwrap_or_default().to_string(),
+            url: source["url"].as_str().unwrap_or_default().to_string(),
+            level: source["level"].as_i64().unwrap_or_default(),
+            product_count: source["product_count"].as_i64().unwrap_or_default(),
+            children: vec![],
+        }
+    }
+}
+
+/**
+ * Category menu and category search, both read only visible categories from the category index
+ */
+pub struct CategorySearch<'a> {
+    index_repository: &'a IndexRepository,
+}
+
+impl<'a> CategorySearch<'a> {
+    pub fn new(index_repository: &'a IndexRepository) -> Self {
+        CategorySearch { index_repository }
+    }
+
+    /**
+     * Tree of visible categories ordered by lft, a category with a hidden parent is left out with its parent
+     */
+    pub async fn get_menu(
+        &self,
+        index_definition: &IndexDefinition,
+    ) -> Result<Vec<CategoryMenuItem>, ElasticError> {
+        let body = json!({
+            "query": { "bool": { "filter": [{ "term": { "visible": true } }] } },
+            "sort": [{ "lft": "asc" }],
+            "_source": ["id", "name", "slug", "url", "parent_id", "level", "product_count"],
+            "size": CATEGORY_MENU_LIMIT,
+            "track_total_hits": false,
+        });
+        let response_body = self
+            .index_repository
+            .search(&index_definition.get_index_alias(), body)
+            .await?;
+
+        let mut children_by_parent_id: BTreeMap<Option<i64>, Vec<CategoryMenuItem>> =
+            BTreeMap::new();
+        // top level categories are children of the root category, which is not exported
+        let mut root_category_id = None;
+        let mut min_level = i64::MAX;
+        for source in get_sources(&response_body) {
+            let item = CategoryMenuItem::from_source(source);
+            if item.level < min_level {
+                min_level = item.level;
+                root_category_id = source["parent_id"].as_i64();
+            }
+            children_by_parent_id
+                .entry(source["parent_id"].as_i64())
+                .or_default()
+                .push(item);
+        }
+        let top_level_items = children_by_parent_id
+            .remove(&root_category_id)
+            .unwrap_or_default();
+
+        Ok(top_level_items
+            .into_iter()
+            .map(|item| with_children(item, &mut children_by_parent_id))
+            .collect())
+    }
+
+    /**
+     * Visible categories by the name, returns "_source" of the documents
+     */
+    pub async fn search(
+        &self,
+        index_definition: &IndexDefinition,
+        text: &str,
+        limit: u32,
+    ) -> Result<Vec<Value>, ElasticError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let body = json!({
+            "query": {
+                "bool": {
+                    "must": {
+                        "multi_match": {
+                            "query": text,
+                            "fields": CATEGORY_SEARCH_FIELDS,
+                        }
+                    },
+                    "filter": [{ "term": { "visible": true } }],
+                }
+            },
+            "_source": ["id", "name", "slug", "url", "parent_id", "level", "product_count"],
+            "size": limit,
+            "track_total_hits": false,
+        });
+        let response_body = self
+            .index_repository
+            .search(&index_definition.get_index_alias(), body)
+            .await?;
+
+        Ok(get_sources(&response_body).cloned().collect())
+    }
+}
+
+fn with_children(
+    mut item: CategoryMenuItem,
+    children_by_parent_id: &mut BTreeMap<Option<i64>, Vec<CategoryMenuItem>>,
+) -> CategoryMenuItem {
+    item.children = children_by_parent_id
+        .remove(&Some(item.id))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| with_children(child, children_by_parent_id))
+        .collect();
+    item
+}
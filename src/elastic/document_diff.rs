@@ -0,0 +1,287 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/**
+ * Number of document ids kept as examples for each difference, the counts are always complete
+ */
+const EXAMPLE_IDS_LIMIT: usize = 10;
+
+/**
+ * Documents of the comparison, the sample is chosen randomly from the documents in the index
+ */
+pub enum DocumentSelection {
+    All,
+    Ids(Vec<i32>),
+    Sample(u32),
+}
+
+/**
+ * Differences of one field over all compared documents, nested fields are joined by dots, e.g. "prices[].price_with_vat"
+ */
+#[derive(Debug, Default, Serialize)]
+pub struct FieldDifference {
+    pub missing_in_index: usize,
+    pub missing_in_export: usize,
+    pub type_mismatches: usize,
+    pub value_differences: usize,
+    pub example_ids: Vec<i32>,
+}
+
+#[derive(Debug, PartialEq)]
+enum DifferenceKind {
+    MissingInIndex,
+    MissingInExport,
+    TypeMismatch,
+    ValueDifference,
+}
+
+/**
+ * Comparison of documents built by the exporter with the documents currently in the index
+ *
+ * Exported document is the expected one, "missing in index" means the field is exported but the index does not have it.
+ */
+#[derive(Debug, Default, Serialize)]
+pub struct DocumentDiffReport {
+    pub compared_count: usize,
+    pub identical_count: usize,
+    pub documents_missing_in_index: usize,
+    pub documents_missing_in_export: usize,
+    pub missing_document_example_ids: Vec<i32>,
+    pub fields: BTreeMap<String, FieldDifference>,
+}
+
+impl DocumentDiffReport {
+    pub fn add_documents(&mut self, id: i32, exported: Option<&Value>, indexed: Option<&Value>) {
+        let (exported, indexed) = match (exported, indexed) {
+            (Some(exported), Some(indexed)) => (exported, indexed),
+            (Some(_), None) => {
+                self.documents_missing_in_index += 1;
+                self.add_missing_document_example(id);
+                return;
+            }
+            (None, Some(_)) => {
+                self.add_document_missing_in_export(id);
+                return;
+            }
+            (None, None) => return,
+        };
+
+        self.compared_count += 1;
+        let mut differences = vec![];
+        compare_values("", exported, indexed, &mut differences);
+        if differences.is_empty() {
+            self.identical_count += 1;
+        }
+
+        for (field, kind) in differences {
+            let field_difference = self.fields.entry(field).or_default();
+            match kind {
+                DifferenceKind::MissingInIndex => field_difference.missing_in_index += 1,
+                DifferenceKind::MissingInExport => field_difference.missing_in_export += 1,
+                DifferenceKind::TypeMismatch => field_difference.type_mismatches += 1,
+                DifferenceKind::ValueDifference => field_difference.value_differences += 1,
+            }
+            if field_difference.example_ids.len() < EXAMPLE_IDS_LIMIT
+                && !field_difference.example_ids.contains(&id)
+            {
+                field_difference.example_ids.push(id);
+            }
+        }
+    }
+
+    /**
+     * Document is in the index but the exporter does not build it anymore, e.g. a product hidden on the domain
+     */
+    pub fn add_document_missing_in_export(&mut self, id: i32) {
+        self.documents_missing_in_export += 1;
+        self.add_missing_document_example(id);
+    }
+
+    pub fn has_differences(&self) -> bool {
+        !self.fields.is_empty()
+            || self.documents_missing_in_index > 0
+            || self.documents_missing_in_export > 0
+    }
+
+    fn add_missing_document_example(&mut self, id: i32) {
+        if self.missing_document_example_ids.len() < EXAMPLE_IDS_LIMIT {
+            self.missing_document_example_ids.push(id);
+        }
+    }
+}
+
+/**
+ * Objects are compared field by field, arrays of objects item by item,
+ * arrays of scalars regardless of the order (e.g. ids of flags are not sorted by the PHP exporter)
+ */
+fn compare_values(
+    path: &str,
+    exported: &Value,
+    indexed: &Value,
+    differences: &mut Vec<(String, DifferenceKind)>,
+) {
+    match (exported, indexed) {
+        (Value::Object(exported), Value::Object(indexed)) => {
+            let keys: BTreeSet<&String> = exported.keys().chain(indexed.keys()).collect();
+            for key in keys {
+                let field = match path.is_empty() {
+                    true => key.clone(),
+                    false => format!("{path}.{key}"),
+                };
+                match (exported.get(key), indexed.get(key)) {
+                    (Some(exported), Some(indexed)) => {
+                        compare_values(&field, exported, indexed, differences)
+                    }
+                    (Some(_), None) => differences.push((field, DifferenceKind::MissingInIndex)),
+                    (None, Some(_)) => differences.push((field, DifferenceKind::MissingInExport)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(exported), Value::Array(indexed)) => {
+            let has_objects = exported.iter().chain(indexed).any(Value::is_object);
+            if has_objects && exported.len() == indexed.len() {
+                for (exported, indexed) in exported.iter().zip(indexed) {
+                    compare_values(&format!("{path}[]"), exported, indexed, differences);
+                }
+            } else if get_sorted_items(exported) != get_sorted_items(indexed) {
+                differences.push((path.to_string(), DifferenceKind::ValueDifference));
+            }
+        }
+        (Value::Number(exported), Value::Number(indexed)) => {
+            // 1 and 1.0 are the same number in the index
+            if exported.as_f64() != indexed.as_f64() {
+                differences.push((path.to_string(), DifferenceKind::ValueDifference));
+            }
+        }
+        (exported, indexed) if get_type_name(exported) != get_type_name(indexed) => {
+            differences.push((path.to_string(), DifferenceKind::TypeMismatch));
+        }
+        (exported, indexed) => {
+            if exported != indexed {
+                differences.push((path.to_string(), DifferenceKind::ValueDifference));
+            }
+        }
+    }
+}
+
+fn get_sorted_items(items: &[Value]) -> Vec<String> {
+    let mut items: Vec<String> = items.iter().map(Value::to_string).collect();
+    items.sort();
+    items
+}
+
+fn get_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn get_differences(exported: &Value, indexed: &Value) -> Vec<(String, DifferenceKind)> {
+        let mut differences = vec![];
+        compare_values("", exported, indexed, &mut differences);
+        differences
+    }
+
+    #[test]
+    fn same_documents() {
+        let differences = get_differences(
+            &json!({ "id": 1, "price": 100, "flags": [1, 2], "prices": [{ "price": 1.5 }] }),
+            &json!({ "id": 1, "price": 100.0, "flags": [2, 1], "prices": [{ "price": 1.5 }] }),
+        );
+
+        assert_eq!(differences, vec![]);
+    }
+
+    #[test]
+    fn missing_fields() {
+        let differences = get_differences(
+            &json!({ "id": 1, "name": "Kolo" }),
+            &json!({ "id": 1, "fullname": "Kolo" }),
+        );
+
+        assert_eq!(
+            differences,
+            vec![
+                ("fullname".to_string(), DifferenceKind::MissingInExport),
+                ("name".to_string(), DifferenceKind::MissingInIndex),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_mismatch_and_value_difference() {
+        let differences = get_differences(
+            &json!({ "brand": 12, "name": "Kolo", "flags": [1, 2] }),
+            &json!({ "brand": "12", "name": "Kolo XL", "flags": [1] }),
+        );
+
+        assert_eq!(
+            differences,
+            vec![
+                ("brand".to_string(), DifferenceKind::TypeMismatch),
+                ("flags".to_string(), DifferenceKind::ValueDifference),
+                ("name".to_string(), DifferenceKind::ValueDifference),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_objects_and_arrays_of_objects() {
+        let differences = get_differences(
+            &json!({
+                "main_category": { "id": 5 },
+                "prices": [{ "pricing_group_id": 1, "price": 100 }],
+                "variants": [{ "id": 2 }],
+            }),
+            &json!({
+                "main_category": { "id": 6 },
+                "prices": [{ "pricing_group_id": 1 }],
+                "variants": [{ "id": 2 }, { "id": 3 }],
+            }),
+        );
+
+        // arrays of objects of different length are compared as a whole
+        assert_eq!(
+            differences,
+            vec![
+                (
+                    "main_category.id".to_string(),
+                    DifferenceKind::ValueDifference
+                ),
+                ("prices[].price".to_string(), DifferenceKind::MissingInIndex),
+                ("variants".to_string(), DifferenceKind::ValueDifference),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_counts_documents_and_fields() {
+        let mut report = DocumentDiffReport::default();
+
+        report.add_documents(1, Some(&json!({ "id": 1 })), Some(&json!({ "id": 1 })));
+        report.add_documents(2, Some(&json!({ "id": 2 })), Some(&json!({ "id": "2" })));
+        report.add_documents(3, Some(&json!({ "id": 3 })), None);
+        report.add_document_missing_in_export(4);
+
+        assert_eq!(report.compared_count, 2);
+        assert_eq!(report.identical_count, 1);
+        assert_eq!(report.documents_missing_in_index, 1);
+        assert_eq!(report.documents_missing_in_export, 1);
+        assert_eq!(report.missing_document_example_ids, vec![3, 4]);
+        assert_eq!(report.fields["id"].type_mismatches, 1);
+        assert_eq!(report.fields["id"].example_ids, vec![2]);
+        assert!(report.has_differences());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::elastic::error::ElasticError;
+use crate::settings::settings::ElasticsearchSettings;
+use elasticsearch::auth::Credentials;
+use elasticsearch::cert::{Certificate, CertificateValidation};
+use elasticsearch::http::headers::{HeaderValue, AUTHORIZATION};
+use elasticsearch::http::transport::{
+    Connection, ConnectionPool, SingleNodeConnectionPool, TransportBuilder,
+};
+use elasticsearch::http::Url;
+use elasticsearch::Elasticsearch;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/**
+ * Client of the nodes from settings, Elasticsearch::default() would connect to localhost:9200 without auth
+ *
+ * API key is either "id:api_key", or already base64 encoded as Kibana shows it
+ */
+pub fn create_client(settings: &ElasticsearchSettings) -> Result<Elasticsearch, ElasticError> {
+    let urls = settings
+        .get_node_urls()
+        .iter()
+        .map(|url| {
+            Url::parse(url).map_err(|err| ElasticError::Transport(format!("{}: {}", url, err)))
+        })
+        .collect::<Result<Vec<Url>, ElasticError>>()?;
+
+    let mut transport_builder = match urls.len() {
+        1 => TransportBuilder::new(SingleNodeConnectionPool::new(urls[0].clone())),
+        _ => TransportBuilder::new(RoundRobinConnectionPool::new(urls)?),
+    };
+    transport_builder = transport_builder.timeout(Duration::from_secs(settings.timeout_secs));
+
+    if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+        transport_builder =
+            transport_builder.auth(Credentials::Basic(username.clone(), password.clone()));
+    }
+    if let Some(api_key) = &settings.api_key {
+        transport_builder = match api_key.split_once(':') {
+            Some((id, api_key)) => {
+                transport_builder.auth(Credentials::ApiKey(id.to_string(), api_key.to_string()))
+            }
+            None => transport_builder.header(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("ApiKey {}", api_key))
+                    .map_err(|err| ElasticError::Transport(err.to_string()))?,
+            ),
+        };
+    }
+    if let Some(ca_certificate_path) = &settings.ca_certificate_path {
+        transport_builder = transport_builder.cert_validation(CertificateValidation::Full(
+            load_certificate(ca_certificate_path)?,
+        ));
+    }
+
+    let transport = transport_builder
+        .build()
+        .map_err(|err| ElasticError::Transport(err.to_string()))?;
+
+    Ok(Elasticsearch::new(transport))
+}
+
+/**
+ * PEM file of the CA that signed the certificates of the nodes, e.g. http_ca.crt generated by Elasticsearch
+ */
+fn load_certificate(path: &str) -> Result<Certificate, ElasticError> {
+    let pem =
+        std::fs::read(path).map_err(|err| ElasticError::Transport(format!("{}: {}", path, err)))?;
+
+    Certificate::from_pem(&pem).map_err(|err| ElasticError::Transport(format!("{}: {}", path, err)))
+}
+
+/**
+ * Requests are spread over all configured nodes one after another
+ *
+ * The 7.x client has only single node and cloud pools. Nodes are not sniffed and dead nodes are not
+ * skipped, so the list should contain only nodes behind which the cluster is reachable.
+ */
+#[derive(Debug, Clone)]
+pub struct RoundRobinConnectionPool {
+    connections: Vec<Connection>,
+    next_index: Arc<AtomicUsize>,
+}
+
+impl RoundRobinConnectionPool {
+    pub fn new(urls: Vec<Url>) -> Result<Self, ElasticError> {
+        if urls.is_empty() {
+            return Err(ElasticError::Transport(
+                "at least one node has to be configured".to_string(),
+            ));
+        }
+
+        Ok(RoundRobinConnectionPool {
+            connections: urls.into_iter().map(Connection::new).collect(),
+            next_index: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+}
+
+impl ConnectionPool for RoundRobinConnectionPool {
+    fn next(&self) -> &Connection {
+        let index = self.next_index.fetch_add(1, Ordering::Relaxed);
+        &self.connections[index % self.connections.len()]
+    }
+}
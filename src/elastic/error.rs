@@ -7,12 +7,72 @@ pub enum ElasticError {
     NoIndexFoundForAlias,
     MoreThanOneIndexFoundForAlias,
     IndexAlreadyExists,
+    DefinitionNotFound(String),
+    InvalidDefinition(String),
+    BulkItemsFailed(usize),
+    Client(elasticsearch::Error),
+    Transport(String),
+    UnexpectedStatus(u16),
+    Output(String),
+    InvalidDocument(i32, String),
+    Postgres(sqlx::Error),
+    DomainNotConfigured(u8),
+    DocumentIdOutOfRange(i64),
+    TaskFailed(String),
+    BatchFailed(String),
 }
 
 impl Error for ElasticError {}
 
 impl Display for ElasticError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Elastic error..")
+        match self {
+            ElasticError::NoAlias => write!(f, "Alias does not exist"),
+            ElasticError::NoIndexFoundForAlias => write!(f, "No index found for alias"),
+            ElasticError::MoreThanOneIndexFoundForAlias => {
+                write!(f, "More than one index found for alias")
+            }
+            ElasticError::IndexAlreadyExists => write!(f, "Index already exists"),
+            ElasticError::DefinitionNotFound(path) => {
+                write!(f, "Index definition '{path}' not found")
+            }
+            ElasticError::InvalidDefinition(message) => {
+                write!(f, "Invalid index definition: {message}")
+            }
+            ElasticError::BulkItemsFailed(count) => {
+                write!(f, "{count} items of bulk request failed")
+            }
+            ElasticError::Client(err) => write!(f, "Elasticsearch client error: {err}"),
+            ElasticError::Transport(message) => {
+                write!(f, "Elasticsearch client could not be created: {message}")
+            }
+            ElasticError::UnexpectedStatus(status) => {
+                write!(f, "Elasticsearch responded with status {status}")
+            }
+            ElasticError::Output(message) => {
+                write!(f, "Bulk output could not be written: {message}")
+            }
+            ElasticError::InvalidDocument(id, message) => {
+                write!(f, "Document '{id}' does not match the mapping: {message}")
+            }
+            ElasticError::Postgres(err) => {
+                write!(f, "Export data could not be read from Postgres: {err}")
+            }
+            ElasticError::DomainNotConfigured(domain_id) => {
+                write!(f, "Domain '{domain_id}' is not configured")
+            }
+            ElasticError::DocumentIdOutOfRange(id) => {
+                write!(
+                    f,
+                    "Document id '{id}' does not fit into the integer ids of the index"
+                )
+            }
+            ElasticError::TaskFailed(message) => {
+                write!(f, "Elasticsearch task failed: {message}")
+            }
+            ElasticError::BatchFailed(message) => {
+                write!(f, "Export batch did not finish: {message}")
+            }
+        }
     }
 }
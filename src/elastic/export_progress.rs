@@ -0,0 +1,136 @@
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+/**
+ * Without a terminal (cron, CI, docker logs) the progress is printed as a line after this interval
+ */
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/**
+ * Result of one exported batch, durations are measured separately for reading and for the bulk request
+ */
+pub struct BatchStats {
+    pub count: usize,
+    pub last_processed_id: u32,
+    pub postgres_duration: Duration,
+    pub elasticsearch_duration: Duration,
+}
+
+pub struct ExportSummary {
+    pub exported_count: u64,
+    pub duration: Duration,
+}
+
+/**
+ * Progress of IndexFacade::export - progress bar on a terminal, periodic log lines otherwise
+ *
+ * Postgres and Elasticsearch durations are summed over all batches, with more batches exported
+ * at once they can be longer than the elapsed time.
+ */
+pub struct ExportProgress {
+    bar: Option<ProgressBar>,
+    total_count: u64,
+    exported_count: u64,
+    postgres_duration: Duration,
+    elasticsearch_duration: Duration,
+    started_at: Instant,
+    last_logged_at: Instant,
+}
+
+impl ExportProgress {
+    pub fn new(total_count: u64) -> Self {
+        let bar = std::io::stderr().is_terminal().then(|| {
+            let bar = ProgressBar::new(total_count);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template(
+                        "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%) {per_sec} ETA: {eta} {msg}",
+                    )
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            bar
+        });
+
+        ExportProgress {
+            bar,
+            total_count,
+            exported_count: 0,
+            postgres_duration: Duration::ZERO,
+            elasticsearch_duration: Duration::ZERO,
+            started_at: Instant::now(),
+            last_logged_at: Instant::now(),
+        }
+    }
+
+    pub fn add_batch(&mut self, batch_stats: &BatchStats) {
+        self.exported_count += batch_stats.count as u64;
+        self.postgres_duration += batch_stats.postgres_duration;
+        self.elasticsearch_duration += batch_stats.elasticsearch_duration;
+
+        match &self.bar {
+            Some(bar) => {
+                bar.set_message(self.get_durations_message());
+                bar.inc(batch_stats.count as u64);
+            }
+            None if self.last_logged_at.elapsed() >= LOG_INTERVAL => {
+                self.last_logged_at = Instant::now();
+                tracing::info!(
+                    "Exported {}/{} documents ({:.0}%), {:.1} docs/s, {}",
+                    self.exported_count,
+                    self.total_count,
+                    self.get_percent(),
+                    self.get_documents_per_second(),
+                    self.get_durations_message()
+                );
+            }
+            None => {}
+        }
+    }
+
+    /**
+     * Summary is logged in both modes, so the log of a non-interactive export ends with the totals too
+     */
+    pub fn finish(self) -> ExportSummary {
+        if let Some(bar) = &self.bar {
+            bar.finish();
+        }
+
+        let duration = self.started_at.elapsed();
+        tracing::info!(
+            "Exported {} documents in {} ({:.1} docs/s), {}",
+            self.exported_count,
+            HumanDuration(duration),
+            self.get_documents_per_second(),
+            self.get_durations_message()
+        );
+
+        ExportSummary {
+            exported_count: self.exported_count,
+            duration,
+        }
+    }
+
+    fn get_durations_message(&self) -> String {
+        format!(
+            "Postgres: {:.2?}, Elasticsearch: {:.2?}",
+            self.postgres_duration, self.elasticsearch_duration
+        )
+    }
+
+    fn get_documents_per_second(&self) -> f64 {
+        let elapsed_secs = self.started_at.elapsed().as_secs_f64();
+        match elapsed_secs > 0.0 {
+            true => self.exported_count as f64 / elapsed_secs,
+            false => 0.0,
+        }
+    }
+
+    fn get_percent(&self) -> f64 {
+        match self.total_count {
+            0 => 100.0,
+            total_count => self.exported_count as f64 * 100.0 / total_count as f64,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::domain::domain_config::DomainConfig;
+use crate::elastic::error::ElasticError;
+use crate::postgres::snapshot::begin_read_only_transaction;
+use sqlx::{Pool, Postgres, Transaction};
+
+/**
+ * Postgres access shared by the indexes of domain-aware entities (products, categories, brands, articles)
+ *
+ * Domains are needed for the locale of translations and the pricing group, Index methods get only the domain id.
+ */
+#[derive(Clone)]
+pub struct ExportSource {
+    pool: Pool<Postgres>,
+    domains: Vec<DomainConfig>,
+    snapshot_id: Option<String>,
+}
+
+impl ExportSource {
+    pub fn new(pool: Pool<Postgres>, domains: Vec<DomainConfig>) -> Self {
+        Self {
+            pool,
+            domains,
+            snapshot_id: None,
+        }
+    }
+
+    /**
+     * Reads all batches from the snapshot exported by ExportSnapshot, the snapshot must stay open during the export
+     */
+    pub fn with_snapshot(mut self, snapshot_id: &str) -> Self {
+        self.snapshot_id = Some(snapshot_id.to_string());
+        self
+    }
+
+    /**
+     * Every batch is read in one REPEATABLE READ transaction, from the snapshot when it is set
+     */
+    #[tracing::instrument(name = "begin_transaction_query", level = "debug", skip_all, fields(snapshot_id = self.snapshot_id.as_deref()))]
+    pub async fn begin_transaction(&self) -> Result<Transaction<'static, Postgres>, ElasticError> {
+        begin_read_only_transaction(&self.pool, self.snapshot_id.as_deref())
+            .await
+            .map_err(ElasticError::Postgres)
+    }
+
+    pub fn get_pool(&self) -> &Pool<Postgres> {
+        &self.pool
+    }
+
+    pub fn get_domain(&self, domain_id: u8) -> Result<&DomainConfig, ElasticError> {
+        self.domains
+            .iter()
+            .find(|domain| domain.id == domain_id)
+            .ok_or(ElasticError::DomainNotConfigured(domain_id))
+    }
+
+    pub fn get_locale(&self, domain_id: u8) -> Result<&str, ElasticError> {
+        Ok(self.get_domain(domain_id)?.locale.as_str())
+    }
+}
@@ -0,0 +1,49 @@
+use super::error::ElasticError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+
+/**
+ * Shopsys AbstractIndex - source of documents of one index, e.g. ProductIndex
+ *
+ * The definition of the index is in {definitions_directory}/{name}/{domain_id}.json.
+ * Futures have to be Send, because IndexFacade exports more batches at once in spawned tasks.
+ * Database errors are returned as ElasticError::Postgres and fail the export.
+ */
+pub trait Index: Clone + Send + Sync + 'static {
+    type Document: Serialize + Send + Sync + 'static;
+
+    /**
+     * AbstractIndex::getName(): string
+     */
+    fn name() -> &'static str;
+
+    /**
+     * AbstractIndex::getTotalCount(int $domainId): int
+     */
+    fn get_total_count(
+        &self,
+        domain_id: u8,
+    ) -> impl Future<Output = Result<i64, ElasticError>> + Send;
+
+    /**
+     * AbstractIndex::getExportDataForBatch(int $domainId, int $lastProcessedId, int $batchSize): array
+     */
+    fn get_export_data_for_batch(
+        &self,
+        domain_id: u8,
+        last_processed_id: u32,
+        batch_size: u32,
+    ) -> impl Future<Output = Result<BTreeMap<i32, Self::Document>, ElasticError>> + Send;
+
+    /**
+     * AbstractIndex::getExportDataForIds(int $domainId, array $restrictToIds): array
+     *
+     * Ids missing in the result are not exported anymore and are deleted from the index
+     */
+    fn get_export_data_for_ids(
+        &self,
+        domain_id: u8,
+        ids: Vec<i32>,
+    ) -> impl Future<Output = Result<BTreeMap<i32, Self::Document>, ElasticError>> + Send;
+}
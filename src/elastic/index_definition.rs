@@ -1,3 +1,7 @@
+use crate::elastic::error::ElasticError;
+use serde_json::Value;
+use std::path::PathBuf;
+
 #[derive(Clone)]
 pub struct IndexDefinition {
     pub(crate) index_name: String,
@@ -7,6 +11,14 @@ pub struct IndexDefinition {
 }
 
 impl IndexDefinition {
+    pub fn get_index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    pub fn get_domain_id(&self) -> u8 {
+        self.domain_id
+    }
+
     pub fn get_index_alias(&self) -> String {
         match self.index_prefix.is_empty() {
             true => format!("{}_{}", self.index_name, self.domain_id),
@@ -16,4 +28,46 @@ impl IndexDefinition {
             ),
         }
     }
+
+    /**
+     * {definitions_directory}/{index_name}/{domain_id}.json
+     */
+    pub fn get_definition_filepath(&self) -> PathBuf {
+        PathBuf::from(&self.definitions_directory)
+            .join(&self.index_name)
+            .join(format!("{}.json", self.domain_id))
+    }
+
+    /**
+     * IndexDefinition::getDefinition(): array
+     */
+    pub fn get_definition(&self) -> Result<Value, ElasticError> {
+        let filepath = self.get_definition_filepath();
+        let content = std::fs::read_to_string(&filepath)
+            .map_err(|_| ElasticError::DefinitionNotFound(filepath.display().to_string()))?;
+
+        serde_json::from_str(&content).map_err(|err| {
+            ElasticError::InvalidDefinition(format!("{}: {}", filepath.display(), err))
+        })
+    }
+
+    /**
+     * Hash of the normalized definition, so formatting changes of the file do not trigger migration
+     */
+    pub fn get_definition_hash(&self) -> Result<String, ElasticError> {
+        let definition = self.get_definition()?;
+
+        Ok(format!("{:x}", md5::compute(definition.to_string())))
+    }
+
+    /**
+     * IndexDefinition::getVersionedIndexName(): string, e.g. shopsys_product_1_8125c598e02572a44ac644251e00b25d
+     */
+    pub fn get_versioned_index_name(&self) -> Result<String, ElasticError> {
+        Ok(format!(
+            "{}_{}",
+            self.get_index_alias(),
+            self.get_definition_hash()?
+        ))
+    }
 }
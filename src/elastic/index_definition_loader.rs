@@ -1,4 +1,8 @@
+use crate::elastic::error::ElasticError;
 use crate::elastic::index_definition::IndexDefinition;
+use crate::settings::settings::ElasticsearchSettings;
+
+pub const DEFAULT_DEFINITIONS_DIRECTORY: &str = "./src/resources/definition/";
 
 pub struct IndexDefinitionLoader {
     directory: String,
@@ -13,12 +17,51 @@ impl IndexDefinitionLoader {
         }
     }
 
-    pub fn get_definition(self, index_name: String, domain_id: u8) -> IndexDefinition {
+    pub fn from_settings(settings: &ElasticsearchSettings) -> Self {
+        Self::new(
+            settings.definitions_directory.clone(),
+            settings.index_prefix.clone(),
+        )
+    }
+
+    pub fn get_definition(&self, index_name: String, domain_id: u8) -> IndexDefinition {
         IndexDefinition {
             index_name,
-            definitions_directory: self.directory,
-            index_prefix: self.index_prefix,
+            definitions_directory: self.directory.clone(),
+            index_prefix: self.index_prefix.clone(),
             domain_id,
         }
     }
+
+    /**
+     * Names of all indexes, each subdirectory of the definitions directory is one index
+     */
+    pub fn get_index_names(&self) -> Result<Vec<String>, ElasticError> {
+        let entries = std::fs::read_dir(&self.directory)
+            .map_err(|_| ElasticError::DefinitionNotFound(self.directory.clone()))?;
+
+        let mut index_names = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect::<Vec<String>>();
+        index_names.sort();
+
+        Ok(index_names)
+    }
+
+    /**
+     * Definitions of all indexes on all given domains
+     */
+    pub fn get_definitions(&self, domain_ids: &[u8]) -> Result<Vec<IndexDefinition>, ElasticError> {
+        Ok(self
+            .get_index_names()?
+            .into_iter()
+            .flat_map(|index_name| {
+                domain_ids
+                    .iter()
+                    .map(move |domain_id| self.get_definition(index_name.clone(), *domain_id))
+            })
+            .collect())
+    }
 }
@@ -1,136 +1,452 @@
+use super::document_diff::{DocumentDiffReport, DocumentSelection};
 use super::error::ElasticError;
+use super::export_progress::{BatchStats, ExportProgress};
+use super::index::Index;
 use super::index_definition::IndexDefinition;
 use super::index_repository::IndexRepository;
-use super::product_index::ProductIndex;
-use indicatif::HumanDuration;
-use std::cmp::{max, min};
+use super::index_status::{IndexStatus, IndexStatusReport};
+use super::mapping_validator::MappingValidator;
+use crate::metrics::metrics::{EXPORTED_DOCUMENTS_TOTAL, EXPORT_BATCH_DURATION_SECONDS};
+use crate::settings::settings::ExportSettings;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::time::Instant;
+use std::time::Instant;
+use tracing::Instrument;
 
 pub struct IndexFacade<'a> {
     index_repository: &'a IndexRepository,
+    export_settings: ExportSettings,
 }
 
 impl<'a> IndexFacade<'a> {
     pub fn new(index_repository: &'a IndexRepository) -> Self {
-        IndexFacade { index_repository }
+        IndexFacade {
+            index_repository,
+            export_settings: ExportSettings::default(),
+        }
     }
 
-    pub fn create(&self, index_definition: &IndexDefinition) {}
+    /**
+     * Batch size and number of batches exported at once
+     */
+    pub fn with_export_settings(mut self, export_settings: &ExportSettings) -> Self {
+        self.export_settings = export_settings.clone();
+        self
+    }
 
-    pub async fn export(&self, product_index: &ProductIndex, index_definition: &IndexDefinition) {
-        println!(
-            "Exporting data of '{}' on domain '{}'",
-            index_definition.index_name, index_definition.domain_id
+    /**
+     * IndexFacade::create(IndexDefinition $indexDefinition, OutputInterface $output): void
+     */
+    pub async fn create(&self, index_definition: &IndexDefinition) -> Result<(), ElasticError> {
+        tracing::info!(
+            "Creating index '{}' on domain '{}'",
+            index_definition.index_name,
+            index_definition.domain_id
         );
 
-        self.create_index_when_no_alias_found(index_definition);
+        self.index_repository.create_index(index_definition).await?;
+        self.index_repository.create_alias(index_definition).await
+    }
 
-        let bar = indicatif::ProgressBar::new(650000);
-        bar.set_style(
-            indicatif::ProgressStyle::default_bar()
-                .template(
-                    "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%) ETA: {eta} Postgres: {msg}",
-                )
-                .unwrap()
-                .progress_chars("=> "),
+    /**
+     * IndexFacade::delete(IndexDefinition $indexDefinition, OutputInterface $output): void
+     */
+    pub async fn delete(&self, index_definition: &IndexDefinition) -> Result<(), ElasticError> {
+        tracing::info!(
+            "Deleting index '{}' on domain '{}'",
+            index_definition.index_name,
+            index_definition.domain_id
         );
-        let mut last_processed_id = 0;
-        let batch_size = 100;
 
-        let mut postgres_duration = Duration::new(0, 0);
-        let n_max = 3;
+        self.index_repository
+            .delete_index_by_index_definition(index_definition)
+            .await
+    }
+
+    /**
+     * IndexFacade::export(AbstractIndex $index, IndexDefinition $indexDefinition, OutputInterface $output): void
+     */
+    #[tracing::instrument(
+        name = "export",
+        skip_all,
+        fields(
+            index = %index_definition.index_name,
+            domain = index_definition.domain_id,
+            doc_count = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        )
+    )]
+    pub async fn export<I: Index>(
+        &self,
+        index: &I,
+        index_definition: &IndexDefinition,
+    ) -> Result<(), ElasticError> {
+        tracing::info!(
+            "Exporting data of '{}' on domain '{}'",
+            index_definition.index_name,
+            index_definition.domain_id
+        );
 
-        let single_thread = true;
+        if !self.index_repository.is_dry_run() {
+            self.create_index_when_no_alias_found(index_definition)
+                .await?;
+        }
+
+        let mapping_validator = self.get_mapping_validator(index_definition)?;
+        let total_count = index.get_total_count(index_definition.domain_id).await?;
+        let mut progress = ExportProgress::new(total_count.max(0) as u64);
+        let mut last_processed_id = 0;
+        let batch_size = self.export_settings.batch_size;
+        let n_max = self.export_settings.concurrency.max(1) as usize;
 
         loop {
-            if single_thread {
-                // let now = Instant::now();
-                let current_batch_data = product_index
-                    .get_export_data_for_batch(
-                        index_definition.domain_id,
-                        last_processed_id,
-                        batch_size,
-                    )
-                    .await;
-                // postgres_duration += now.elapsed();
-                // bar.set_message(format!("{}", HumanDuration(postgres_duration)));
+            // batches of one round export disjoint id ranges, only the last one continues up to its limit,
+            // so a document is never exported twice when ids have gaps
+            let mut tasks = Vec::with_capacity(n_max);
+            for n in 0..n_max {
+                let index_clone = index.clone();
+                let index_definition_clone = index_definition.clone();
+                let index_repository_clone = self.index_repository.clone();
+                let after_id = last_processed_id + (n as u32 * batch_size);
+                let max_id = (n + 1 < n_max).then_some(after_id + batch_size);
+                tasks.push(tokio::spawn(get_batch_and_bulk_update(
+                    index_repository_clone,
+                    mapping_validator.clone(),
+                    index_clone,
+                    index_definition_clone,
+                    after_id,
+                    max_id,
+                    batch_size,
+                )));
+            }
 
-                self.index_repository
-                    .bulk_update(index_definition, &current_batch_data)
-                    .await;
+            let mut outputs = Vec::with_capacity(n_max);
+            for task in tasks {
+                outputs.push(
+                    task.await
+                        .map_err(|err| ElasticError::BatchFailed(err.to_string()))??,
+                );
+            }
 
-                if let Some(last_key) = current_batch_data.keys().last() {
-                    last_processed_id = *last_key as u32;
-                }
+            for batch_stats in &outputs {
+                progress.add_batch(batch_stats);
+            }
+            let last_batch_stats = outputs.last().unwrap();
+            last_processed_id = last_batch_stats.last_processed_id;
 
-                bar.inc(current_batch_data.len() as u64);
+            // the last batch of the round is not limited by max_id, so it is the first to run out of documents
+            if (last_batch_stats.count as u32) < batch_size {
+                break;
+            }
+        }
 
-                if (current_batch_data.len() as u32) < batch_size {
-                    break;
-                }
-            } else {
-                let mut tasks = Vec::with_capacity(n_max);
-                for n in (0..n_max) {
-                    let product_index_clone = product_index.clone();
-                    let index_definition_clone = index_definition.clone();
-                    let index_repository_clone = self.index_repository.clone();
-                    tasks.push(tokio::spawn(get_batch_and_bulk_update(
-                        index_repository_clone,
-                        product_index_clone,
-                        index_definition_clone,
-                        last_processed_id + (n as u32 * batch_size),
-                        batch_size,
-                    )));
-                }
+        let summary = progress.finish();
+        tracing::Span::current()
+            .record("doc_count", summary.exported_count)
+            .record("duration_ms", summary.duration.as_millis() as u64);
+        Ok(())
+    }
 
-                let mut outputs = Vec::with_capacity(n_max);
-                for task in tasks {
-                    outputs.push(task.await.unwrap());
-                }
+    /**
+     * IndexFacade::exportIds(AbstractIndex $index, IndexDefinition $indexDefinition, array $restrictToIds): void
+     *
+     * Ids that are no longer exported (e.g. product was hidden or deleted) are removed from the index
+     */
+    pub async fn export_ids<I: Index>(
+        &self,
+        index: &I,
+        index_definition: &IndexDefinition,
+        ids: &[i32],
+    ) -> Result<(), ElasticError> {
+        tracing::info!(
+            "Exporting {} ids of '{}' on domain '{}'",
+            ids.len(),
+            index_definition.index_name,
+            index_definition.domain_id
+        );
 
-                last_processed_id = outputs.iter().map(|i| i.1 as u32).max().unwrap();
+        let mapping_validator = self.get_mapping_validator(index_definition)?;
+        for ids_to_export in ids.chunks(self.export_settings.batch_size as usize) {
+            let current_batch_data = index
+                .get_export_data_for_ids(index_definition.domain_id, ids_to_export.to_vec())
+                .await?;
+            validate_documents(mapping_validator.as_deref(), &current_batch_data)?;
 
-                bar.inc(outputs.iter().map(|i| i.0 as u64).sum());
+            self.index_repository
+                .bulk_update(index_definition, &current_batch_data)
+                .await?;
 
-                if (outputs.iter().map(|i| i.0 as u32).min().unwrap()) < batch_size {
-                    break;
-                }
-            }
+            let ids_to_delete = ids_to_export
+                .iter()
+                .filter(|id| !current_batch_data.contains_key(id))
+                .copied()
+                .collect::<Vec<i32>>();
+            self.index_repository
+                .delete_ids(index_definition, &ids_to_delete)
+                .await?;
         }
 
-        bar.finish();
-        return;
-        todo!("is_index_up_to_date > migrate")
+        Ok(())
     }
 
-    pub async fn migrate(&self, index_definition: &IndexDefinition) {
-        let existing_index_name = self.resolve_existing_index_name(index_definition).await;
-        if let Err(ElasticError::NoAlias) = existing_index_name {
-            println!(
-                "No index for alias \"{}\" was not found on domain \"{}\"",
-                index_definition.index_name, index_definition.domain_id
+    /**
+     * IndexFacade::migrate(IndexDefinition $indexDefinition, OutputInterface $output): void
+     *
+     * When the definition changed, data are reindexed into a new index and the alias is switched to it
+     */
+    pub async fn migrate(&self, index_definition: &IndexDefinition) -> Result<(), ElasticError> {
+        let existing_index_name = match self.resolve_existing_index_name(index_definition).await {
+            Err(ElasticError::NoAlias) => {
+                tracing::info!(
+                    "No index for alias '{}' was found on domain '{}'",
+                    index_definition.get_index_alias(),
+                    index_definition.domain_id
+                );
+                return self.create(index_definition).await;
+            }
+            existing_index_name => existing_index_name?,
+        };
+
+        let new_index_name = index_definition.get_versioned_index_name()?;
+        if existing_index_name == new_index_name {
+            tracing::info!(
+                "Index '{}' on domain '{}' is up to date",
+                index_definition.index_name,
+                index_definition.domain_id
             );
-            self.create(index_definition);
-            return;
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Migrating index '{}' on domain '{}'",
+            index_definition.index_name,
+            index_definition.domain_id
+        );
+        self.index_repository.create_index(index_definition).await?;
+        if let Err(err) = self
+            .reindex_and_switch_alias(index_definition, &existing_index_name, &new_index_name)
+            .await
+        {
+            // alias still points to the existing index, the half filled new index would block the next migrate
+            if let Err(delete_err) = self.index_repository.delete_index(&new_index_name).await {
+                tracing::warn!("Index '{new_index_name}' could not be deleted: {delete_err}");
+            }
+            return Err(err);
         }
 
-        let existing_index_name = existing_index_name.unwrap();
+        self.index_repository
+            .delete_index(&existing_index_name)
+            .await
     }
 
-    async fn create_index_when_no_alias_found(&self, index_definition: &IndexDefinition) {
-        if let Err(ElasticError::NoAlias) = self
-            .index_repository
-            .find_current_index_name_for_alias(&index_definition.get_index_alias())
+    async fn reindex_and_switch_alias(
+        &self,
+        index_definition: &IndexDefinition,
+        existing_index_name: &str,
+        new_index_name: &str,
+    ) -> Result<(), ElasticError> {
+        self.index_repository
+            .reindex(existing_index_name, new_index_name)
+            .await?;
+        self.index_repository
+            .switch_alias(
+                &index_definition.get_index_alias(),
+                existing_index_name,
+                new_index_name,
+            )
             .await
-        {
-            panic!(
-                "Index '{}' does not exist on domain '{}'",
-                index_definition.index_name, index_definition.domain_id
+    }
+
+    /**
+     * Documents built by the index compared with the documents behind the current alias, nothing is written
+     */
+    pub async fn diff<I: Index>(
+        &self,
+        index: &I,
+        index_definition: &IndexDefinition,
+        document_selection: &DocumentSelection,
+    ) -> Result<DocumentDiffReport, ElasticError> {
+        tracing::info!(
+            "Comparing documents of '{}' on domain '{}'",
+            index_definition.index_name,
+            index_definition.domain_id
+        );
+
+        let index_alias = index_definition.get_index_alias();
+        let batch_size = self.export_settings.batch_size;
+        let mut report = DocumentDiffReport::default();
+
+        let ids = match document_selection {
+            DocumentSelection::All => {
+                let mut last_processed_id = 0;
+                let mut exported_ids = BTreeSet::new();
+                loop {
+                    let current_batch_data = index
+                        .get_export_data_for_batch(
+                            index_definition.domain_id,
+                            last_processed_id,
+                            batch_size,
+                        )
+                        .await?;
+                    let ids: Vec<i32> = current_batch_data.keys().copied().collect();
+                    self.compare_documents(&index_alias, &ids, &current_batch_data, &mut report)
+                        .await?;
+                    exported_ids.extend(ids.iter().copied());
+
+                    if let Some(last_key) = ids.last() {
+                        last_processed_id = *last_key as u32;
+                    }
+                    if (ids.len() as u32) < batch_size {
+                        break;
+                    }
+                }
+
+                // stale documents are only in the index, the export never gets to them
+                let indexed_ids = self
+                    .index_repository
+                    .get_all_document_ids(&index_alias, batch_size)
+                    .await?;
+                for id in indexed_ids.difference(&exported_ids) {
+                    report.add_document_missing_in_export(*id);
+                }
+
+                return Ok(report);
+            }
+            DocumentSelection::Ids(ids) => ids.clone(),
+            DocumentSelection::Sample(size) => {
+                self.index_repository
+                    .get_random_document_ids(&index_alias, *size)
+                    .await?
+            }
+        };
+
+        for ids in ids.chunks(batch_size as usize) {
+            let current_batch_data = index
+                .get_export_data_for_ids(index_definition.domain_id, ids.to_vec())
+                .await?;
+            self.compare_documents(&index_alias, ids, &current_batch_data, &mut report)
+                .await?;
+        }
+
+        Ok(report)
+    }
+
+    async fn compare_documents<D: Serialize>(
+        &self,
+        index_alias: &str,
+        ids: &[i32],
+        current_batch_data: &BTreeMap<i32, D>,
+        report: &mut DocumentDiffReport,
+    ) -> Result<(), ElasticError> {
+        let indexed_documents = self
+            .index_repository
+            .get_documents(index_alias, ids)
+            .await?;
+        for id in ids {
+            report.add_documents(
+                *id,
+                current_batch_data
+                    .get(id)
+                    .map(|document| json!(document))
+                    .as_ref(),
+                indexed_documents.get(id),
             );
-        } else {
-            todo!("self.create")
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Status of each definition and all our indexes that are not behind any alias
+     */
+    pub async fn get_status_report(
+        &self,
+        index_definitions: &[IndexDefinition],
+    ) -> Result<IndexStatusReport, ElasticError> {
+        let (indexes_info, aliases_by_index) = tokio::try_join!(
+            self.index_repository.get_indexes_info(),
+            self.index_repository.get_aliases_by_index()
+        )?;
+
+        let indexes = index_definitions
+            .iter()
+            .map(|index_definition| {
+                let alias = index_definition.get_index_alias();
+                let index = aliases_by_index
+                    .iter()
+                    .find(|(_, aliases)| aliases.contains(&alias))
+                    .map(|(index, _)| index.clone());
+                let index_info = indexes_info
+                    .iter()
+                    .find(|index_info| Some(&index_info.index) == index.as_ref());
+                let expected_index = index_definition.get_versioned_index_name().ok();
+
+                IndexStatus {
+                    index_name: index_definition.index_name.clone(),
+                    domain_id: index_definition.domain_id,
+                    is_up_to_date: index.is_some() && index == expected_index,
+                    docs_count: index_info.and_then(|index_info| index_info.docs_count),
+                    store_size_bytes: index_info.and_then(|index_info| index_info.store_size_bytes),
+                    health: index_info.and_then(|index_info| index_info.health.clone()),
+                    alias,
+                    index,
+                    expected_index,
+                }
+            })
+            .collect();
+
+        let orphaned_indexes = indexes_info
+            .into_iter()
+            .filter(|index_info| is_own_index(&index_info.index, index_definitions))
+            .filter(|index_info| {
+                aliases_by_index
+                    .get(&index_info.index)
+                    .is_none_or(Vec::is_empty)
+            })
+            .collect();
+
+        Ok(IndexStatusReport {
+            indexes,
+            orphaned_indexes,
+        })
+    }
+
+    /**
+     * Validator of the definition when the export is strict, None otherwise
+     */
+    fn get_mapping_validator(
+        &self,
+        index_definition: &IndexDefinition,
+    ) -> Result<Option<Arc<MappingValidator>>, ElasticError> {
+        if !self.export_settings.strict_mapping {
+            return Ok(None);
+        }
+
+        let definition = index_definition.get_definition()?;
+        Ok(Some(Arc::new(MappingValidator::from_definition(
+            &definition,
+        )?)))
+    }
+
+    /**
+     * IndexFacade::createIndexWhenNoAliasFound(IndexDefinition $indexDefinition, OutputInterface $output): void
+     */
+    async fn create_index_when_no_alias_found(
+        &self,
+        index_definition: &IndexDefinition,
+    ) -> Result<(), ElasticError> {
+        match self.resolve_existing_index_name(index_definition).await {
+            Err(ElasticError::NoAlias) => {
+                tracing::info!(
+                    "Index '{}' does not exist on domain '{}'",
+                    index_definition.index_name,
+                    index_definition.domain_id
+                );
+                self.create(index_definition).await
+            }
+            existing_index_name => existing_index_name.map(|_| ()),
         }
     }
 
@@ -144,23 +460,119 @@ impl<'a> IndexFacade<'a> {
     }
 }
 
-async fn get_batch_and_bulk_update(
+/**
+ * Index was created for one of the definitions, i.e. it has the prefix or starts with the alias
+ */
+fn is_own_index(index: &str, index_definitions: &[IndexDefinition]) -> bool {
+    index_definitions.iter().any(|index_definition| {
+        index.starts_with(&format!("{}_", index_definition.get_index_alias()))
+            || (!index_definition.index_prefix.is_empty()
+                && index.starts_with(&format!("{}_", index_definition.index_prefix)))
+    })
+}
+
+/**
+ * One batch read from Postgres and sent to Elasticsearch, spawned so more batches can be exported at once
+ *
+ * Documents with id above max_id belong to the next batch of the round and are left for it.
+ */
+#[tracing::instrument(
+    name = "export_batch",
+    skip_all,
+    fields(
+        index = %index_definition.index_name,
+        domain = index_definition.domain_id,
+        after_id = last_processed_id,
+        max_id = ?max_id,
+        last_id = tracing::field::Empty,
+        doc_count = tracing::field::Empty,
+        postgres_ms = tracing::field::Empty,
+        elasticsearch_ms = tracing::field::Empty,
+    )
+)]
+async fn get_batch_and_bulk_update<I: Index>(
     index_repository: IndexRepository,
-    product_index: ProductIndex,
+    mapping_validator: Option<Arc<MappingValidator>>,
+    index: I,
     index_definition: IndexDefinition,
     last_processed_id: u32,
+    max_id: Option<u32>,
     batch_size: u32,
-) -> (usize, i32) {
-    let current_batch_data = product_index
+) -> Result<BatchStats, ElasticError> {
+    let now = Instant::now();
+    let mut current_batch_data = index
         .get_export_data_for_batch(index_definition.domain_id, last_processed_id, batch_size)
-        .await;
+        .instrument(tracing::debug_span!("export_data_query", batch_size))
+        .await?;
+    let postgres_duration = now.elapsed();
+    if let Some(max_id) = max_id {
+        current_batch_data.split_off(&(max_id as i32 + 1));
+    }
+
+    validate_documents(mapping_validator.as_deref(), &current_batch_data)?;
 
+    let now = Instant::now();
     index_repository
         .bulk_update(&index_definition, &current_batch_data)
-        .await;
+        .await?;
+    let elasticsearch_duration = now.elapsed();
+
+    let batch_stats = BatchStats {
+        count: current_batch_data.len(),
+        last_processed_id: current_batch_data
+            .keys()
+            .last()
+            .map_or(last_processed_id, |last_key| *last_key as u32),
+        postgres_duration,
+        elasticsearch_duration,
+    };
+
+    let domain = index_definition.domain_id.to_string();
+    EXPORTED_DOCUMENTS_TOTAL
+        .with_label_values(&[&index_definition.index_name, &domain])
+        .inc_by(batch_stats.count as u64);
+    EXPORT_BATCH_DURATION_SECONDS
+        .with_label_values(&[&index_definition.index_name, "postgres"])
+        .observe(postgres_duration.as_secs_f64());
+    EXPORT_BATCH_DURATION_SECONDS
+        .with_label_values(&[&index_definition.index_name, "elasticsearch"])
+        .observe(elasticsearch_duration.as_secs_f64());
+    tracing::Span::current()
+        .record("last_id", batch_stats.last_processed_id)
+        .record("doc_count", batch_stats.count)
+        .record("postgres_ms", postgres_duration.as_millis() as u64)
+        .record(
+            "elasticsearch_ms",
+            elasticsearch_duration.as_millis() as u64,
+        );
+
+    Ok(batch_stats)
+}
+
+/**
+ * First invalid document fails the export, before anything of its batch is sent
+ */
+fn validate_documents<D: Serialize>(
+    mapping_validator: Option<&MappingValidator>,
+    current_batch_data: &BTreeMap<i32, D>,
+) -> Result<(), ElasticError> {
+    let Some(mapping_validator) = mapping_validator else {
+        return Ok(());
+    };
+
+    for (id, document) in current_batch_data {
+        let violations = mapping_validator.validate(&json!(document));
+        if !violations.is_empty() {
+            return Err(ElasticError::InvalidDocument(
+                *id,
+                violations
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ));
+        }
+    }
 
-    return (
-        current_batch_data.len(),
-        *current_batch_data.keys().last().unwrap(),
-    );
+    Ok(())
 }
@@ -1,38 +1,95 @@
+use crate::elastic::bulk_sink::BulkSink;
+use crate::elastic::elasticsearch_client::create_client;
 use crate::elastic::error::ElasticError;
 use crate::elastic::index_definition::IndexDefinition;
-use crate::elastic::product_index::ProductExportData;
+use crate::elastic::index_status::IndexInfo;
+use crate::metrics::metrics::{BULK_FAILURES_TOTAL, SEARCH_DURATION_SECONDS};
+use crate::settings::settings::ElasticsearchSettings;
 use elasticsearch::cat::CatIndicesParts;
-use elasticsearch::indices::{IndicesExistsAliasParts, IndicesExistsParts, IndicesGetAliasParts};
-use elasticsearch::{BulkOperation, BulkOperations, BulkParts, Elasticsearch};
+use elasticsearch::http::response::Response;
+use elasticsearch::indices::{
+    IndicesCreateParts, IndicesDeleteParts, IndicesExistsAliasParts, IndicesExistsParts,
+    IndicesGetAliasParts, IndicesPutAliasParts,
+};
+use elasticsearch::params::Bytes;
+use elasticsearch::tasks::TasksGetParts;
+use elasticsearch::{
+    BulkOperation, BulkOperations, BulkParts, ClearScrollParts, Elasticsearch, GetParts, MgetParts,
+    ScrollParts, SearchParts,
+};
+use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+/**
+ * How long the search context of a scroll is kept between two pages
+ */
+const SCROLL_KEEP_ALIVE: &str = "1m";
+
+/**
+ * How often the task of a long running request (e.g. _reindex) is checked
+ */
+const TASK_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Clone)]
 pub struct IndexRepository {
     pub(crate) client: Elasticsearch,
+    bulk_sink: BulkSink,
 }
 
 impl IndexRepository {
-    pub async fn is_index_created(&self, index_name: &str) -> bool {
-        self.client
+    pub fn new(client: Elasticsearch) -> Self {
+        IndexRepository {
+            client,
+            bulk_sink: BulkSink::default(),
+        }
+    }
+
+    /**
+     * Documents of bulk_update and delete_ids go to the sink instead of Elasticsearch, e.g. for a dry-run export
+     */
+    pub fn with_bulk_sink(mut self, bulk_sink: BulkSink) -> Self {
+        self.bulk_sink = bulk_sink;
+        self
+    }
+
+    /**
+     * Export does not touch the cluster at all, not even to create a missing index
+     */
+    pub fn is_dry_run(&self) -> bool {
+        !self.bulk_sink.is_elasticsearch()
+    }
+
+    /**
+     * Repository with the client configured by settings (nodes, auth, CA, timeout)
+     */
+    pub fn from_settings(settings: &ElasticsearchSettings) -> Result<Self, ElasticError> {
+        Ok(Self::new(create_client(settings)?))
+    }
+
+    pub async fn is_index_created(&self, index_name: &str) -> Result<bool, ElasticError> {
+        Ok(self
+            .client
             .indices()
             .exists(IndicesExistsParts::Index(&[index_name]))
             .send()
             .await
-            .unwrap()
+            .map_err(ElasticError::Client)?
             .status_code()
-            .is_success()
+            .is_success())
     }
 
-    pub async fn is_alias_created(&self, index_alias: &str) -> bool {
-        self.client
+    pub async fn is_alias_created(&self, index_alias: &str) -> Result<bool, ElasticError> {
+        Ok(self
+            .client
             .indices()
             .exists_alias(IndicesExistsAliasParts::Name(&[index_alias]))
             .send()
             .await
-            .unwrap()
+            .map_err(ElasticError::Client)?
             .status_code()
-            .is_success()
+            .is_success())
     }
 
     /**
@@ -127,24 +184,64 @@ impl IndexRepository {
 
     /**
      * CAT api is mainly for plain/text results. But I am using 'format(json)' here and using it to get all indicies
-     * This shouldn't be prefered method, but showing that it is possible.
+     * with their health, document count and store size (in bytes, so it can be compared and summed).
      * CAT API: https://www.elastic.co/guide/en/elasticsearch/reference/7.17/cat.html
      */
-    pub async fn get_all_indicies_by_cat_api_and_print_them(&self) {
+    pub async fn get_indexes_info(&self) -> Result<Vec<IndexInfo>, ElasticError> {
         let response = self
             .client
             .cat()
             .indices(CatIndicesParts::Index(&["*"]))
             .format("json")
+            .bytes(Bytes::B)
             .send()
             .await
-            .unwrap();
+            .map_err(ElasticError::Client)?;
 
-        let response_body = response.json::<Value>().await.unwrap();
-        for record in response_body.as_array().unwrap() {
-            // print the name of each index
-            println!("{}", record["index"].as_str().unwrap());
-        }
+        let response_body = check_status(response)?
+            .json::<Value>()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        Ok(response_body
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(IndexInfo::from_cat_record)
+            .collect())
+    }
+
+    /**
+     * Index name => aliases of the index, for all indexes
+     */
+    pub async fn get_aliases_by_index(
+        &self,
+    ) -> Result<BTreeMap<String, Vec<String>>, ElasticError> {
+        let response = self
+            .client
+            .indices()
+            .get_alias(IndicesGetAliasParts::None)
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        let response_body = check_status(response)?
+            .json::<Value>()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        Ok(response_body
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(index, aliases_object)| {
+                let aliases = aliases_object["aliases"]
+                    .as_object()
+                    .map(|aliases| aliases.keys().cloned().collect())
+                    .unwrap_or_default();
+                (index.clone(), aliases)
+            })
+            .collect())
     }
 
     /**
@@ -154,7 +251,7 @@ impl IndexRepository {
         &self,
         index_alias: &str,
     ) -> Result<Vec<String>, ElasticError> {
-        if !self.is_alias_created(index_alias).await {
+        if !self.is_alias_created(index_alias).await? {
             return Err(ElasticError::NoAlias);
         }
 
@@ -165,15 +262,13 @@ impl IndexRepository {
             .get_alias(IndicesGetAliasParts::Name(&[index_alias]))
             .send()
             .await
-            .unwrap()
+            .map_err(ElasticError::Client)?
             .json::<Value>()
             .await
-            .unwrap()
+            .map_err(ElasticError::Client)?
             .as_object()
-            .unwrap()
-            .keys()
-            .cloned()
-            .collect::<Vec<String>>();
+            .map(|indexes| indexes.keys().cloned().collect::<Vec<String>>())
+            .unwrap_or_default();
 
         if indexes_with_alias.is_empty() {
             return Err(ElasticError::NoIndexFoundForAlias);
@@ -182,45 +277,282 @@ impl IndexRepository {
         Ok(indexes_with_alias)
     }
 
-    pub async fn bulk_update(
+    #[tracing::instrument(
+        name = "bulk_request",
+        skip_all,
+        fields(
+            index = %index_definition.get_index_alias(),
+            doc_count = current_batch_data.len(),
+            duration_ms = tracing::field::Empty,
+        )
+    )]
+    pub async fn bulk_update<D: Serialize>(
         &self,
         index_definition: &IndexDefinition,
-        current_batch_data: &BTreeMap<i32, ProductExportData>,
-    ) {
+        current_batch_data: &BTreeMap<i32, D>,
+    ) -> Result<(), ElasticError> {
         if current_batch_data.is_empty() {
-            return;
+            return Ok(());
         }
+        if self.is_dry_run() {
+            return self
+                .bulk_sink
+                .write_updates(&index_definition.get_index_alias(), current_batch_data);
+        }
+        let started_at = Instant::now();
 
         let mut ops = BulkOperations::new();
-        // let mut counter = 0;
         for (id, data) in current_batch_data {
-            // ops.push(BulkOperation::update(id.to_string(), data))
-            //     .unwrap();
-
-            // if counter % 1000 == 0 {
-            //     println!("{}: {}", id, json!(data));
-            // }
-
-            // dbg!(data);
             ops.push(BulkOperation::update(
                 id.to_string(),
                 json!({
                     "doc": json!(data),
                     "doc_as_upsert": true
                 }),
-            ));
-            // print!("{}, ", id);
-            // counter += 1;
+            ))
+            .map_err(ElasticError::Client)?;
+        }
+
+        let response = self
+            .client
+            .bulk(BulkParts::Index(&index_definition.get_index_alias()))
+            .body(vec![ops])
+            .send()
+            .await
+            .map_err(ElasticError::Client);
+
+        let result = match response {
+            Ok(response) => check_bulk_response(response).await,
+            Err(err) => Err(err),
+        };
+        tracing::Span::current().record("duration_ms", started_at.elapsed().as_millis() as u64);
+        if let Err(err) = &result {
+            tracing::warn!("Bulk request failed: {err}");
+            BULK_FAILURES_TOTAL
+                .with_label_values(&[&index_definition.get_index_alias()])
+                .inc();
+        }
+
+        result
+    }
+
+    /**
+     * IndexRepository::deleteIds(string $indexAlias, array $ids): void
+     */
+    pub async fn delete_ids(
+        &self,
+        index_definition: &IndexDefinition,
+        ids: &[i32],
+    ) -> Result<(), ElasticError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        if self.is_dry_run() {
+            return self
+                .bulk_sink
+                .write_deletes(&index_definition.get_index_alias(), ids);
         }
 
-        self.client
+        let mut ops = BulkOperations::new();
+        for id in ids {
+            ops.push(BulkOperation::<Value>::delete(id.to_string()))
+                .map_err(ElasticError::Client)?;
+        }
+
+        let response = self
+            .client
             .bulk(BulkParts::Index(&index_definition.get_index_alias()))
             .body(vec![ops])
             .send()
-            .await;
+            .await
+            .map_err(ElasticError::Client)?;
+
+        check_bulk_response(response).await
+    }
+
+    /**
+     * IndexRepository::createIndex(IndexDefinition $indexDefinition): void
+     */
+    pub async fn create_index(
+        &self,
+        index_definition: &IndexDefinition,
+    ) -> Result<(), ElasticError> {
+        let index_name = index_definition.get_versioned_index_name()?;
+        if self.is_index_created(&index_name).await? {
+            return Err(ElasticError::IndexAlreadyExists);
+        }
+
+        let response = self
+            .client
+            .indices()
+            .create(IndicesCreateParts::Index(&index_name))
+            .body(index_definition.get_definition()?)
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        check_status(response).map(|_| ())
+    }
+
+    /**
+     * IndexRepository::createAlias(IndexDefinition $indexDefinition): void
+     */
+    pub async fn create_alias(
+        &self,
+        index_definition: &IndexDefinition,
+    ) -> Result<(), ElasticError> {
+        let index_name = index_definition.get_versioned_index_name()?;
+
+        let response = self
+            .client
+            .indices()
+            .put_alias(IndicesPutAliasParts::IndexName(
+                &[&index_name],
+                &index_definition.get_index_alias(),
+            ))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        check_status(response).map(|_| ())
+    }
+
+    /**
+     * Moves the alias from the old index to the new one in a single request, so searches never hit both
+     */
+    pub async fn switch_alias(
+        &self,
+        index_alias: &str,
+        old_index_name: &str,
+        new_index_name: &str,
+    ) -> Result<(), ElasticError> {
+        let response = self
+            .client
+            .indices()
+            .update_aliases()
+            .body(json!({
+                "actions": [
+                    { "remove": { "index": old_index_name, "alias": index_alias } },
+                    { "add": { "index": new_index_name, "alias": index_alias } },
+                ]
+            }))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        check_status(response).map(|_| ())
+    }
+
+    /**
+     * IndexRepository::reindex(string $oldIndexName, string $newIndexName): void
+     *
+     * Reindex of a big index takes longer than the request timeout, so it runs as a task that is polled
+     */
+    pub async fn reindex(
+        &self,
+        old_index_name: &str,
+        new_index_name: &str,
+    ) -> Result<(), ElasticError> {
+        let response = self
+            .client
+            .reindex()
+            .wait_for_completion(false)
+            .body(json!({
+                "source": { "index": old_index_name },
+                "dest": { "index": new_index_name },
+            }))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        let response_body = check_status(response)?
+            .json::<Value>()
+            .await
+            .map_err(ElasticError::Client)?;
+        let task_id = response_body["task"]
+            .as_str()
+            .ok_or_else(|| ElasticError::TaskFailed("_reindex returned no task".to_string()))?;
+
+        let task = self.wait_for_task(task_id).await?;
+        if let Some(reason) = task["error"]["reason"].as_str() {
+            return Err(ElasticError::TaskFailed(format!("{task_id}: {reason}")));
+        }
+
+        match task["response"]["failures"].as_array() {
+            Some(failures) if !failures.is_empty() => {
+                Err(ElasticError::BulkItemsFailed(failures.len()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Result of the task once it is completed, polling itself has no timeout
+     */
+    async fn wait_for_task(&self, task_id: &str) -> Result<Value, ElasticError> {
+        loop {
+            let response = self
+                .client
+                .tasks()
+                .get(TasksGetParts::TaskId(task_id))
+                .send()
+                .await
+                .map_err(ElasticError::Client)?;
+
+            let task = check_status(response)?
+                .json::<Value>()
+                .await
+                .map_err(ElasticError::Client)?;
+            if task["completed"].as_bool() == Some(true) {
+                return Ok(task);
+            }
+
+            tokio::time::sleep(TASK_POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn delete_index(&self, index_name: &str) -> Result<(), ElasticError> {
+        let response = self
+            .client
+            .indices()
+            .delete(IndicesDeleteParts::Index(&[index_name]))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        check_status(response).map(|_| ())
+    }
+
+    /**
+     * IndexRepository::deleteIndexByIndexDefinition(IndexDefinition $indexDefinition): void
+     *
+     * Deletes all indexes behind the alias and also the versioned index, if it exists without alias
+     */
+    pub async fn delete_index_by_index_definition(
+        &self,
+        index_definition: &IndexDefinition,
+    ) -> Result<(), ElasticError> {
+        let mut index_names = match self
+            .find_index_names_for_alias(&index_definition.get_index_alias())
+            .await
+        {
+            Ok(index_names) => index_names,
+            Err(ElasticError::NoAlias) | Err(ElasticError::NoIndexFoundForAlias) => vec![],
+            Err(err) => return Err(err),
+        };
+
+        let versioned_index_name = index_definition.get_versioned_index_name()?;
+        if !index_names.contains(&versioned_index_name)
+            && self.is_index_created(&versioned_index_name).await?
+        {
+            index_names.push(versioned_index_name);
+        }
 
-        // println!("Count: {}", counter);
-        // print!(".");
+        for index_name in index_names {
+            self.delete_index(&index_name).await?;
+        }
+
+        Ok(())
     }
 
     /**
@@ -238,4 +570,222 @@ impl IndexRepository {
 
         Ok(indexes_with_alias[0].clone())
     }
+
+    /**
+     * Runs the given search request body against the index alias and returns the raw response body
+     */
+    pub async fn search(&self, index_alias: &str, body: Value) -> Result<Value, ElasticError> {
+        let _timer = SEARCH_DURATION_SECONDS
+            .with_label_values(&[index_alias])
+            .start_timer();
+        let response = self
+            .client
+            .search(SearchParts::Index(&[index_alias]))
+            .body(body)
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        if !response.status_code().is_success() {
+            return Err(ElasticError::UnexpectedStatus(
+                response.status_code().as_u16(),
+            ));
+        }
+
+        response.json::<Value>().await.map_err(ElasticError::Client)
+    }
+
+    /**
+     * "_source" of the found documents by their id, ids that are not in the index are missing in the result
+     */
+    pub async fn get_documents(
+        &self,
+        index_alias: &str,
+        ids: &[i32],
+    ) -> Result<BTreeMap<i32, Value>, ElasticError> {
+        if ids.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let response = self
+            .client
+            .mget(MgetParts::Index(index_alias))
+            .body(json!({ "ids": ids.iter().map(i32::to_string).collect::<Vec<String>>() }))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+        let response_body = check_status(response)?
+            .json::<Value>()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        Ok(response_body["docs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|document| document["found"].as_bool().unwrap_or_default())
+            .filter_map(|document| {
+                let id = document["_id"].as_str()?.parse().ok()?;
+                Some((id, document["_source"].clone()))
+            })
+            .collect())
+    }
+
+    /**
+     * Ids of randomly chosen documents of the alias, e.g. a sample for the comparison of documents
+     */
+    pub async fn get_random_document_ids(
+        &self,
+        index_alias: &str,
+        size: u32,
+    ) -> Result<Vec<i32>, ElasticError> {
+        let body = json!({
+            "query": {
+                "function_score": {
+                    "query": { "match_all": {} },
+                    "random_score": {},
+                }
+            },
+            "_source": false,
+            "size": size,
+        });
+        let response_body = self.search(index_alias, body).await?;
+
+        Ok(response_body["hits"]["hits"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|hit| hit["_id"].as_str()?.parse().ok())
+            .collect())
+    }
+
+    /**
+     * Ids of all documents of the alias, scrolled in pages of the batch size
+     */
+    pub async fn get_all_document_ids(
+        &self,
+        index_alias: &str,
+        batch_size: u32,
+    ) -> Result<BTreeSet<i32>, ElasticError> {
+        let response = self
+            .client
+            .search(SearchParts::Index(&[index_alias]))
+            .scroll(SCROLL_KEEP_ALIVE)
+            .body(json!({ "_source": false, "size": batch_size, "sort": ["_doc"] }))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+        let mut response_body = check_status(response)?
+            .json::<Value>()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        let mut ids = BTreeSet::new();
+        loop {
+            let hits = response_body["hits"]["hits"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            let scroll_id = response_body["_scroll_id"].as_str().map(str::to_string);
+            ids.extend(
+                hits.iter()
+                    .filter_map(|hit| hit["_id"].as_str()?.parse::<i32>().ok()),
+            );
+
+            let Some(scroll_id) = scroll_id else {
+                return Ok(ids);
+            };
+            if hits.is_empty() {
+                self.clear_scroll(&scroll_id).await?;
+                return Ok(ids);
+            }
+
+            let response = self
+                .client
+                .scroll(ScrollParts::None)
+                .body(json!({ "scroll": SCROLL_KEEP_ALIVE, "scroll_id": scroll_id }))
+                .send()
+                .await
+                .map_err(ElasticError::Client)?;
+            response_body = check_status(response)?
+                .json::<Value>()
+                .await
+                .map_err(ElasticError::Client)?;
+        }
+    }
+
+    async fn clear_scroll(&self, scroll_id: &str) -> Result<(), ElasticError> {
+        let response = self
+            .client
+            .clear_scroll(ClearScrollParts::None)
+            .body(json!({ "scroll_id": [scroll_id] }))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        check_status(response).map(|_| ())
+    }
+
+    /**
+     * Returns "_source" of the document, or None when there is no document with such id
+     */
+    pub async fn get_document(
+        &self,
+        index_alias: &str,
+        id: &str,
+    ) -> Result<Option<Value>, ElasticError> {
+        let response = self
+            .client
+            .get(GetParts::IndexId(index_alias, id))
+            .send()
+            .await
+            .map_err(ElasticError::Client)?;
+
+        match response.status_code().as_u16() {
+            404 => Ok(None),
+            status if !response.status_code().is_success() => {
+                Err(ElasticError::UnexpectedStatus(status))
+            }
+            _ => Ok(Some(
+                response
+                    .json::<Value>()
+                    .await
+                    .map_err(ElasticError::Client)?["_source"]
+                    .take(),
+            )),
+        }
+    }
+}
+
+fn check_status(response: Response) -> Result<Response, ElasticError> {
+    match response.status_code().is_success() {
+        true => Ok(response),
+        false => Err(ElasticError::UnexpectedStatus(
+            response.status_code().as_u16(),
+        )),
+    }
+}
+
+/**
+ * Bulk API responds 200 even when some of the items failed, so the items have to be checked one by one
+ */
+async fn check_bulk_response(response: Response) -> Result<(), ElasticError> {
+    let response_body = check_status(response)?
+        .json::<Value>()
+        .await
+        .map_err(ElasticError::Client)?;
+
+    if !response_body["errors"].as_bool().unwrap_or_default() {
+        return Ok(());
+    }
+
+    let failed_count = response_body["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_object()?.values().next())
+        .filter(|operation| operation.get("error").is_some())
+        .count();
+
+    Err(ElasticError::BulkItemsFailed(failed_count))
 }
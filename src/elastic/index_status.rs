@@ -0,0 +1,54 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/**
+ * State of one index definition on one domain
+ */
+#[derive(Debug, Serialize)]
+pub struct IndexStatus {
+    pub index_name: String,
+    pub domain_id: u8,
+    pub alias: String,
+    /** physical index behind the alias, None when the alias does not exist */
+    pub index: Option<String>,
+    /** index name the current definition would create, None when the definition can not be loaded */
+    pub expected_index: Option<String>,
+    pub is_up_to_date: bool,
+    pub docs_count: Option<u64>,
+    pub store_size_bytes: Option<u64>,
+    pub health: Option<String>,
+}
+
+/**
+ * Physical index from _cat/indices
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexInfo {
+    pub index: String,
+    pub health: Option<String>,
+    pub docs_count: Option<u64>,
+    pub store_size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexStatusReport {
+    pub indexes: Vec<IndexStatus>,
+    /** indexes with our prefix that are not behind any alias, e.g. leftovers of failed migrations */
+    pub orphaned_indexes: Vec<IndexInfo>,
+}
+
+impl IndexInfo {
+    /**
+     * _cat API returns all values as strings, store size is in bytes when requested with bytes=b
+     */
+    pub fn from_cat_record(record: &Value) -> Self {
+        let as_u64 = |value: &Value| value.as_str().and_then(|value| value.parse().ok());
+
+        IndexInfo {
+            index: record["index"].as_str().unwrap_or_default().to_string(),
+            health: record["health"].as_str().map(str::to_string),
+            docs_count: as_u64(&record["docs.count"]),
+            store_size_bytes: as_u64(&record["store.size"]),
+        }
+    }
+}
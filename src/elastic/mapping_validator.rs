@@ -0,0 +1,253 @@
+use super::error::ElasticError;
+use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
+
+/**
+ * Checks exported documents against "mappings.properties" of the index definition
+ *
+ * Elasticsearch accepts a lot silently: unknown fields are added by dynamic mapping and "12" is coerced
+ * into an integer field, so such bugs of the export are found only by wrong search results.
+ * Null and arrays are valid for every field, as in Elasticsearch.
+ */
+pub struct MappingValidator {
+    properties: Map<String, Value>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MappingViolation {
+    NotInMapping {
+        field: String,
+    },
+    TypeMismatch {
+        field: String,
+        mapping_type: String,
+        value: Value,
+    },
+    ShapeMismatch {
+        field: String,
+        mapping_type: String,
+    },
+}
+
+impl Display for MappingViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MappingViolation::NotInMapping { field } => {
+                write!(f, "field '{field}' is not in the mapping")
+            }
+            MappingViolation::TypeMismatch {
+                field,
+                mapping_type,
+                value,
+            } => write!(
+                f,
+                "field '{field}' of type '{mapping_type}' has value {value}"
+            ),
+            MappingViolation::ShapeMismatch {
+                field,
+                mapping_type,
+            } => write!(
+                f,
+                "field '{field}' of type '{mapping_type}' is not an object"
+            ),
+        }
+    }
+}
+
+impl MappingValidator {
+    pub fn from_definition(definition: &Value) -> Result<Self, ElasticError> {
+        match definition["mappings"]["properties"].as_object() {
+            Some(properties) => Ok(MappingValidator {
+                properties: properties.clone(),
+            }),
+            None => Err(ElasticError::InvalidDefinition(
+                "mappings.properties is missing".to_string(),
+            )),
+        }
+    }
+
+    /**
+     * Violations of the serialized document, fields of objects are joined by dots, e.g. "prices.price_with_vat"
+     */
+    pub fn validate(&self, document: &Value) -> Vec<MappingViolation> {
+        let mut violations = vec![];
+        match document.as_object() {
+            Some(fields) => validate_object("", fields, &self.properties, &mut violations),
+            None => violations.push(MappingViolation::ShapeMismatch {
+                field: String::new(),
+                mapping_type: "object".to_string(),
+            }),
+        }
+
+        violations
+    }
+}
+
+fn validate_object(
+    path: &str,
+    fields: &Map<String, Value>,
+    properties: &Map<String, Value>,
+    violations: &mut Vec<MappingViolation>,
+) {
+    for (name, value) in fields {
+        let field = match path.is_empty() {
+            true => name.clone(),
+            false => format!("{path}.{name}"),
+        };
+        match properties.get(name) {
+            Some(mapping) => validate_value(&field, value, mapping, violations),
+            None => violations.push(MappingViolation::NotInMapping { field }),
+        }
+    }
+}
+
+fn validate_value(
+    field: &str,
+    value: &Value,
+    mapping: &Value,
+    violations: &mut Vec<MappingViolation>,
+) {
+    if let Value::Array(items) = value {
+        for item in items {
+            validate_value(field, item, mapping, violations);
+        }
+        return;
+    }
+    if value.is_null() {
+        return;
+    }
+
+    // object fields have only "properties" in the mapping
+    let mapping_type = mapping["type"].as_str().unwrap_or("object");
+    let is_valid = match mapping_type {
+        "object" | "nested" => {
+            match (value.as_object(), mapping["properties"].as_object()) {
+                (Some(fields), Some(properties)) => {
+                    validate_object(field, fields, properties, violations)
+                }
+                (Some(_), None) => {}
+                (None, _) => violations.push(MappingViolation::ShapeMismatch {
+                    field: field.to_string(),
+                    mapping_type: mapping_type.to_string(),
+                }),
+            }
+            return;
+        }
+        "integer" | "long" | "short" | "byte" => value.is_i64() || value.is_u64(),
+        "float" | "double" | "half_float" | "scaled_float" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "text" | "keyword" | "icu_collation_keyword" | "wildcard" => value.is_string(),
+        "date" => value.is_string() || value.is_i64(),
+        _ => true,
+    };
+
+    if !is_valid {
+        violations.push(MappingViolation::TypeMismatch {
+            field: field.to_string(),
+            mapping_type: mapping_type.to_string(),
+            value: value.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn get_validator() -> MappingValidator {
+        MappingValidator::from_definition(&json!({
+            "mappings": {
+                "properties": {
+                    "id": { "type": "integer" },
+                    "name": { "type": "text", "fields": { "keyword": { "type": "keyword" } } },
+                    "brand": { "type": "integer" },
+                    "flags": { "type": "integer" },
+                    "in_stock": { "type": "boolean" },
+                    "prices": {
+                        "type": "nested",
+                        "properties": {
+                            "pricing_group_id": { "type": "integer" },
+                            "price_with_vat": { "type": "float" },
+                        }
+                    },
+                    "main_category": { "properties": { "id": { "type": "integer" } } },
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn valid_document() {
+        let violations = get_validator().validate(&json!({
+            "id": 1,
+            "name": "Kolo",
+            "brand": null,
+            "flags": [1, 2],
+            "in_stock": true,
+            "prices": [{ "pricing_group_id": 1, "price_with_vat": 100 }],
+            "main_category": { "id": 5 },
+        }));
+
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    fn stringified_id_in_integer_field() {
+        let violations = get_validator().validate(&json!({ "id": 1, "brand": "12" }));
+
+        assert_eq!(
+            violations,
+            vec![MappingViolation::TypeMismatch {
+                field: "brand".to_string(),
+                mapping_type: "integer".to_string(),
+                value: json!("12"),
+            }]
+        );
+    }
+
+    #[test]
+    fn field_missing_in_mapping() {
+        let violations = get_validator().validate(&json!({ "id": 1, "fullname": "Kolo" }));
+
+        assert_eq!(
+            violations,
+            vec![MappingViolation::NotInMapping {
+                field: "fullname".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn nested_and_object_shapes() {
+        let violations = get_validator().validate(&json!({
+            "prices": [{ "pricing_group_id": "1", "vat": 21 }],
+            "main_category": 5,
+            "flags": [1, 2.5],
+        }));
+
+        assert_eq!(
+            violations,
+            vec![
+                MappingViolation::TypeMismatch {
+                    field: "flags".to_string(),
+                    mapping_type: "integer".to_string(),
+                    value: json!(2.5),
+                },
+                MappingViolation::ShapeMismatch {
+                    field: "main_category".to_string(),
+                    mapping_type: "object".to_string(),
+                },
+                MappingViolation::TypeMismatch {
+                    field: "prices.pricing_group_id".to_string(),
+                    mapping_type: "integer".to_string(),
+                    value: json!("1"),
+                },
+                MappingViolation::NotInMapping {
+                    field: "prices.vat".to_string()
+                },
+            ]
+        );
+    }
+}
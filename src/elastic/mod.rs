@@ -1,7 +1,25 @@
+pub mod article_index;
+pub mod article_search;
+pub mod brand_index;
+pub mod bulk_sink;
+pub mod category_index;
+pub mod category_search;
+#[allow(dead_code)]
 mod discord_experiment;
+pub mod document_diff;
+pub mod elasticsearch_client;
 pub mod error;
+pub mod export_progress;
+pub mod export_source;
+pub mod index;
 pub mod index_definition;
 pub mod index_definition_loader;
 pub mod index_facade;
 pub mod index_repository;
+pub mod index_status;
+pub mod mapping_validator;
+pub mod product_autocomplete;
+pub mod product_filter;
 pub mod product_index;
+pub mod product_query;
+pub mod product_search;
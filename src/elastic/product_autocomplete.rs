@@ -0,0 +1,136 @@
+use super::error::ElasticError;
+use super::index_definition_loader::IndexDefinitionLoader;
+use super::index_repository::IndexRepository;
+use super::product_search::{get_sources, ProductListItem};
+use crate::domain::domain_config::DomainConfig;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+pub const AUTOCOMPLETE_PRODUCTS_LIMIT: u32 = 10;
+pub const AUTOCOMPLETE_PRODUCTS_MAX_LIMIT: u32 = 50;
+const AUTOCOMPLETE_SUGGESTIONS_LIMIT: u32 = 5;
+
+/**
+ * Edge-ngram subfields of the product definition, searched with their "full_*" search analyzers
+ */
+const AUTOCOMPLETE_FIELDS: [&str; 5] = [
+    "searching_names.edge_ngram_with_diacritic^2",
+    "searching_names.edge_ngram_without_diacritic",
+    "searching_catnums.edge_ngram_unanalyzed_words",
+    "searching_partnos.edge_ngram_unanalyzed_words",
+    "searching_eans.edge_ngram_unanalyzed_words",
+];
+
+#[derive(Debug, Serialize)]
+pub struct AutocompleteResult {
+    pub products: Vec<ProductListItem>,
+    pub categories: Vec<Value>,
+    pub brands: Vec<Value>,
+}
+
+pub struct ProductAutocomplete<'a> {
+    index_repository: &'a IndexRepository,
+}
+
+impl<'a> ProductAutocomplete<'a> {
+    pub fn new(index_repository: &'a IndexRepository) -> Self {
+        ProductAutocomplete { index_repository }
+    }
+
+    /**
+     * Products are searched together with category and brand suggestions,
+     * suggestions are left empty when the category or brand index does not exist on the domain
+     */
+    pub async fn autocomplete(
+        &self,
+        index_definition_loader: &IndexDefinitionLoader,
+        domain: &DomainConfig,
+        text: &str,
+        limit: u32,
+    ) -> Result<AutocompleteResult, ElasticError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(AutocompleteResult {
+                products: vec![],
+                categories: vec![],
+                brands: vec![],
+            });
+        }
+
+        let product_alias = index_definition_loader
+            .get_definition("product".to_string(), domain.id)
+            .get_index_alias();
+        let category_alias = index_definition_loader
+            .get_definition("category".to_string(), domain.id)
+            .get_index_alias();
+        let brand_alias = index_definition_loader
+            .get_definition("brand".to_string(), domain.id)
+            .get_index_alias();
+
+        let (products, categories, brands) = tokio::join!(
+            self.index_repository
+                .search(&product_alias, get_products_body(text, limit)),
+            self.search_suggestions(
+                &category_alias,
+                text,
+                json!({ "term": { "visible": true } })
+            ),
+            // brands are exported with no visible products too
+            self.search_suggestions(
+                &brand_alias,
+                text,
+                json!({ "range": { "product_count": { "gt": 0 } } })
+            ),
+        );
+
+        Ok(AutocompleteResult {
+            products: get_sources(&products?)
+                .map(|source| ProductListItem::from_source(source, domain.pricing_group_id))
+                .collect(),
+            categories: categories?,
+            brands: brands?,
+        })
+    }
+
+    /**
+     * Suggestions are matched by name and narrowed by the filter, e.g. to the ones visible on the domain
+     */
+    async fn search_suggestions(
+        &self,
+        index_alias: &str,
+        text: &str,
+        filter: Value,
+    ) -> Result<Vec<Value>, ElasticError> {
+        let body = json!({
+            "query": {
+                "bool": {
+                    "must": { "match_bool_prefix": { "name": text } },
+                    "filter": [filter],
+                }
+            },
+            "_source": ["id", "name", "slug", "url"],
+            "size": AUTOCOMPLETE_SUGGESTIONS_LIMIT,
+            "track_total_hits": false,
+        });
+
+        match self.index_repository.search(index_alias, body).await {
+            Ok(response_body) => Ok(get_sources(&response_body).cloned().collect()),
+            Err(ElasticError::UnexpectedStatus(404)) => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn get_products_body(text: &str, limit: u32) -> Value {
+    json!({
+        "query": {
+            "multi_match": {
+                "query": text,
+                "fields": AUTOCOMPLETE_FIELDS,
+            }
+        },
+        "_source": ["id", "name", "full_name", "catnum", "image_url", "prices"],
+        "size": limit,
+        "track_total_hits": false,
+    })
+}
@@ -0,0 +1,276 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const AGGREGATION_SIZE: u32 = 100;
+
+/**
+ * Shopsys ProductFilterData - selected values of the product list filter,
+ * only facets of fields exported by ProductIndex are supported
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ProductFilterData {
+    pub brands: Vec<i32>,
+    pub flags: Vec<i32>,
+    pub categories: Vec<i32>,
+    pub minimal_price: Option<f64>,
+    pub maximal_price: Option<f64>,
+    pub in_stock: bool,
+    /// parameter_id => selected parameter_value_ids
+    pub parameters: BTreeMap<i32, Vec<i32>>,
+    /// prices of this pricing group are filtered and aggregated, usually the default one of the domain
+    pub pricing_group_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceRange {
+    pub minimal_price: f64,
+    pub maximal_price: f64,
+}
+
+/**
+ * Shopsys ProductFilterCountData - number of products for each filter choice
+ */
+#[derive(Debug, Default, Serialize)]
+pub struct ProductFilterCountData {
+    pub count_by_brand_id: BTreeMap<i32, u64>,
+    pub count_by_flag_id: BTreeMap<i32, u64>,
+    pub count_by_category_id: BTreeMap<i32, u64>,
+    pub count_in_stock: u64,
+    pub count_by_parameter_id_and_value_id: BTreeMap<i32, BTreeMap<i32, u64>>,
+    /// None when no product of the filter has a price
+    pub price_range: Option<PriceRange>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Facet {
+    Brands,
+    Flags,
+    Categories,
+    Prices,
+    Stock,
+    Parameter(i32),
+}
+
+impl ProductFilterData {
+    /**
+     * Filter for the "post_filter" part of the search request, so aggregations are computed without it
+     */
+    pub fn get_post_filter(&self) -> Value {
+        bool_filter(self.get_filter_clauses().iter().map(|(_, c)| c))
+    }
+
+    /**
+     * Every facet is aggregated with all filters applied except its own one,
+     * so the counts tell how many products would be found after checking the choice
+     */
+    pub fn get_aggregations(&self) -> Value {
+        let clauses = self.get_filter_clauses();
+        let mut aggregations = json!({
+            "brands": {
+                "filter": filters_except(&clauses, &Facet::Brands),
+                "aggs": { "brands": terms("brand") },
+            },
+            "flags": {
+                "filter": filters_except(&clauses, &Facet::Flags),
+                "aggs": { "flags": terms("flags") },
+            },
+            "categories": {
+                "filter": filters_except(&clauses, &Facet::Categories),
+                "aggs": { "categories": terms("categories") },
+            },
+            "stock": {
+                "filter": filters_except(&clauses, &Facet::Stock),
+                "aggs": { "in_stock": { "filter": { "term": { "in_stock": true } } } },
+            },
+            "prices": {
+                "filter": filters_except(&clauses, &Facet::Prices),
+                "aggs": {
+                    "prices": {
+                        "nested": { "path": "prices" },
+                        "aggs": {
+                            "pricing_group": {
+                                "filter": { "term": { "prices.pricing_group_id": self.pricing_group_id } },
+                                "aggs": {
+                                    "minimal_price": { "min": { "field": "prices.filtering_minimal_price" } },
+                                    "maximal_price": { "max": { "field": "prices.filtering_maximal_price" } },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "parameters": {
+                "filter": bool_filter(clauses.iter().map(|(_, c)| c)),
+                "aggs": {
+                    "parameters": {
+                        "nested": { "path": "parameters" },
+                        "aggs": {
+                            "by_parameter": {
+                                "terms": { "field": "parameters.parameter_id", "size": AGGREGATION_SIZE },
+                                "aggs": { "by_value": terms("parameters.parameter_value_id") },
+                            },
+                        },
+                    },
+                },
+            },
+        });
+
+        // values of an already filtered parameter are counted without its own filter
+        for parameter_id in self.parameters.keys() {
+            aggregations[format!("parameter_{parameter_id}")] = json!({
+                "filter": filters_except(&clauses, &Facet::Parameter(*parameter_id)),
+                "aggs": {
+                    "parameters": {
+                        "nested": { "path": "parameters" },
+                        "aggs": {
+                            "parameter": {
+                                "filter": { "term": { "parameters.parameter_id": parameter_id } },
+                                "aggs": { "by_value": terms("parameters.parameter_value_id") },
+                            },
+                        },
+                    },
+                },
+            });
+        }
+
+        aggregations
+    }
+
+    fn get_filter_clauses(&self) -> Vec<(Facet, Value)> {
+        let mut clauses = vec![];
+
+        if !self.brands.is_empty() {
+            clauses.push((Facet::Brands, json!({ "terms": { "brand": self.brands } })));
+        }
+        if !self.flags.is_empty() {
+            clauses.push((Facet::Flags, json!({ "terms": { "flags": self.flags } })));
+        }
+        if !self.categories.is_empty() {
+            clauses.push((
+                Facet::Categories,
+                json!({ "terms": { "categories": self.categories } }),
+            ));
+        }
+        if self.minimal_price.is_some() || self.maximal_price.is_some() {
+            clauses.push((Facet::Prices, self.get_prices_clause()));
+        }
+        if self.in_stock {
+            clauses.push((Facet::Stock, json!({ "term": { "in_stock": true } })));
+        }
+        for (parameter_id, value_ids) in &self.parameters {
+            clauses.push((
+                Facet::Parameter(*parameter_id),
+                json!({
+                    "nested": {
+                        "path": "parameters",
+                        "query": bool_filter([
+                            json!({ "term": { "parameters.parameter_id": parameter_id } }),
+                            json!({ "terms": { "parameters.parameter_value_id": value_ids } }),
+                        ].iter()),
+                    }
+                }),
+            ));
+        }
+
+        clauses
+    }
+
+    /**
+     * Shopsys FilterQuery::filterByPrices() - the price range of the product and its variants overlaps the selected one
+     */
+    fn get_prices_clause(&self) -> Value {
+        let mut filters =
+            vec![json!({ "term": { "prices.pricing_group_id": self.pricing_group_id } })];
+        if let Some(minimal_price) = self.minimal_price {
+            filters.push(
+                json!({ "range": { "prices.filtering_maximal_price": { "gte": minimal_price } } }),
+            );
+        }
+        if let Some(maximal_price) = self.maximal_price {
+            filters.push(
+                json!({ "range": { "prices.filtering_minimal_price": { "lte": maximal_price } } }),
+            );
+        }
+
+        json!({
+            "nested": {
+                "path": "prices",
+                "query": bool_filter(filters.iter()),
+            }
+        })
+    }
+}
+
+impl ProductFilterCountData {
+    pub fn from_aggregations(aggregations: &Value) -> Self {
+        let mut count_by_parameter_id_and_value_id = BTreeMap::new();
+        for bucket in buckets(&aggregations["parameters"]["parameters"]["by_parameter"]) {
+            if let Some(parameter_id) = bucket["key"].as_i64() {
+                count_by_parameter_id_and_value_id
+                    .insert(parameter_id as i32, counts_by_key(&bucket["by_value"]));
+            }
+        }
+        for (name, aggregation) in aggregations.as_object().into_iter().flatten() {
+            if let Some(parameter_id) = name
+                .strip_prefix("parameter_")
+                .and_then(|id| id.parse::<i32>().ok())
+            {
+                count_by_parameter_id_and_value_id.insert(
+                    parameter_id,
+                    counts_by_key(&aggregation["parameters"]["parameter"]["by_value"]),
+                );
+            }
+        }
+
+        let pricing_group = &aggregations["prices"]["prices"]["pricing_group"];
+        let price_range = match (
+            pricing_group["minimal_price"]["value"].as_f64(),
+            pricing_group["maximal_price"]["value"].as_f64(),
+        ) {
+            (Some(minimal_price), Some(maximal_price)) => Some(PriceRange {
+                minimal_price,
+                maximal_price,
+            }),
+            _ => None,
+        };
+
+        ProductFilterCountData {
+            count_by_brand_id: counts_by_key(&aggregations["brands"]["brands"]),
+            count_by_flag_id: counts_by_key(&aggregations["flags"]["flags"]),
+            count_by_category_id: counts_by_key(&aggregations["categories"]["categories"]),
+            count_in_stock: aggregations["stock"]["in_stock"]["doc_count"]
+                .as_u64()
+                .unwrap_or_default(),
+            count_by_parameter_id_and_value_id,
+            price_range,
+        }
+    }
+}
+
+fn terms(field: &str) -> Value {
+    json!({ "terms": { "field": field, "size": AGGREGATION_SIZE } })
+}
+
+fn bool_filter<'a>(clauses: impl Iterator<Item = &'a Value>) -> Value {
+    json!({ "bool": { "filter": clauses.collect::<Vec<_>>() } })
+}
+
+fn filters_except(clauses: &[(Facet, Value)], facet: &Facet) -> Value {
+    bool_filter(clauses.iter().filter(|(f, _)| f != facet).map(|(_, c)| c))
+}
+
+fn buckets(aggregation: &Value) -> impl Iterator<Item = &Value> {
+    aggregation["buckets"].as_array().into_iter().flatten()
+}
+
+fn counts_by_key(aggregation: &Value) -> BTreeMap<i32, u64> {
+    buckets(aggregation)
+        .filter_map(|bucket| {
+            Some((
+                bucket["key"].as_i64()? as i32,
+                bucket["doc_count"].as_u64()?,
+            ))
+        })
+        .collect()
+}
@@ -1,9 +1,17 @@
-use crate::elastic::discord_experiment::get_discord;
-use crate::product::product::ProductTranslation;
-use crate::product::product::{Product, ProductDomain};
+use crate::domain::domain_config::DomainConfig;
+use crate::elastic::error::ElasticError;
+use crate::elastic::export_source::ExportSource;
+use crate::elastic::index::Index;
+use crate::product::product::Product;
 use serde::Serialize;
-use sqlx::{Pool, Postgres, Row};
+use sqlx::PgConnection;
 use std::collections::BTreeMap;
+use tracing::Instrument;
+
+/**
+ * Route of the product detail, under which friendly_urls of products are stored
+ */
+pub const PRODUCT_ROUTE_NAME: &str = "front_product_detail";
 
 #[derive(Debug, Serialize)]
 pub struct ProductExportData {
@@ -12,17 +20,66 @@ pub struct ProductExportData {
     partno: String,
     ean: String,
     name: String,
-    fullname: String,
+    full_name: String,
     description: String,
     short_description: String,
-    brand: String,
+    brand: Option<i32>,
     flags: Vec<i32>,
     categories: Vec<i32>,
+    /** main friendly url of the domain, empty when the product has none */
+    slug: String,
+    image_url: Option<String>,
+    availability: Option<String>,
+    /** the product or one of its sellable variants can be dispatched today */
+    in_stock: bool,
+    prices: Vec<ProductPriceExportData>,
+    parameters: Vec<ProductParameterExportData>,
+    /** ids of the sellable variants of a main variant */
+    variants: Vec<i32>,
+    /** copies of the fields above for the analyzers of the full-text search, as in Shopsys ProductExportRepository */
+    searching_names: String,
+    searching_catnums: String,
+    searching_partnos: String,
+    searching_eans: String,
+    searching_short_descriptions: String,
+    searching_descriptions: String,
+}
+
+/**
+ * Calculated price for one of the pricing groups of the domain
+ */
+#[derive(Debug, Serialize)]
+pub struct ProductPriceExportData {
+    pricing_group_id: i32,
+    price_with_vat: f64,
+    /** cheapest and most expensive price of the product and its sellable variants, the price filter runs on them */
+    filtering_minimal_price: f64,
+    filtering_maximal_price: f64,
+}
+
+/**
+ * Parameter with its value in the locale of the domain
+ */
+#[derive(Debug, Serialize)]
+pub struct ProductParameterExportData {
+    parameter_id: i32,
+    parameter_uuid: String,
+    parameter_name: String,
+    parameter_unit: Option<String>,
+    parameter_value_id: i32,
+    parameter_value_uuid: String,
+    parameter_value_text: String,
 }
 
+/**
+ * Shopsys ProductIndex - products visible for the default pricing group of the domain
+ *
+ * All queries for one batch run in a single transaction of the export source,
+ * so translation, flags and categories of a product are read from the same moment.
+ */
 #[derive(Clone)]
 pub struct ProductIndex {
-    pool: Pool<Postgres>,
+    export_source: ExportSource,
 }
 
 #[derive(Debug)]
@@ -49,6 +106,8 @@ pub struct ProductDomainForElasticExport {
     pub short_description: Option<String>,
 }
 
+// main category is not exported yet, see get_export_data_for_products
+#[allow(dead_code)]
 #[derive(Default)]
 pub struct MainCategory {
     id: i32,
@@ -57,26 +116,17 @@ pub struct MainCategory {
 }
 
 impl ProductIndex {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(export_source: ExportSource) -> Self {
+        Self { export_source }
     }
 
-    pub async fn get_export_data_for_batch(
+    async fn get_export_data_for_products(
         &self,
-        domain_id: u8,
-        last_processed_id: u32,
-        batch_size: u32,
-    ) -> BTreeMap<i32, ProductExportData> {
-        // self.get_products_data_macro(domain_id, last_processed_id, batch_size)
-        //     .await;
-        // let products = self
-        //     .get_products_data(domain_id, last_processed_id, batch_size)
-        //     .await;
-
-        let products = self
-            .get_products_data_macro_as(domain_id, last_processed_id, batch_size)
-            .await;
-
+        conn: &mut PgConnection,
+        products: Vec<ProductForElasticExport>,
+        domain: &DomainConfig,
+    ) -> Result<BTreeMap<i32, ProductExportData>, sqlx::Error> {
+        let domain_id = domain.id;
         let mut results: BTreeMap<i32, ProductExportData> = BTreeMap::new();
         for mut product in products {
             // musim mit "mut product" abych pozdej mohl delat nad Option hodnotama take()
@@ -84,61 +134,96 @@ impl ProductIndex {
             // product.with_translation(self.pool.clone()).await;
             // product.with_domain(self.pool.clone()).await;
 
-            get_discord(self.pool.clone()).await;
-
-            let product_translation = self.get_product_translation(product.id).await;
-            let product_domain = self.get_product_domain(product.id).await;
-            let default_pricing_group_id = 1; // todo: vytahnout ze settings
+            let product_translation = self
+                .get_product_translation(conn, product.id, &domain.locale)
+                .await?;
+            let product_domain = self.get_product_domain(conn, product.id, domain_id).await?;
             let variants = self
-                .get_sellable_variants(product.id, 1, default_pricing_group_id)
-                .await;
+                .get_sellable_variants(conn, product.id, domain_id, domain.pricing_group_id)
+                .await?;
             let product_ids = variants
                 .iter()
                 .map(|p| p.id)
                 .chain(std::iter::once(product.id))
-                .collect();
-            let flag_ids = self.extract_flags_for_domain(product_ids, domain_id).await;
-            let main_category = self.get_product_main_category_by_domain_id(product.id, domain_id);
-            let category_ids = self.get_category_ids(product.id, domain_id).await;
+                .collect::<Vec<i32>>();
+            let flag_ids = self
+                .extract_flags_for_domain(conn, product_ids.clone(), domain_id)
+                .await?;
+            // main category is not exported yet (the future was never awaited anyway)
+            // let main_category = self
+            //     .get_product_main_category_by_domain_id(conn, product.id, domain_id)
+            //     .await;
+            let category_ids = self.get_category_ids(conn, product.id, domain_id).await?;
+            let slug = self.get_slug(conn, product.id, domain_id).await?;
+            let image_url = self.get_image_url(conn, product.id).await?;
+            let availability = self
+                .get_availability(conn, product.id, &domain.locale)
+                .await?;
+            let in_stock = self.is_in_stock(conn, &product_ids).await?;
+            let prices = self
+                .get_prices(conn, product.id, &product_ids, domain_id)
+                .await?;
+            let parameters = self
+                .get_parameters(conn, product.id, &domain.locale)
+                .await?;
+
+            let partno = product.partno.take().unwrap_or_default();
+            // take() vezme hodnotu z Option a nahradi za ni None
+            // tim padem neni "partno" uninitialized (neni zde move) a move semantic je pak ok
+            let ean = product.ean.take().unwrap_or_default();
+            let full_name = format!(
+                "{} {} {}",
+                product_translation.name_prefix.unwrap_or_default(),
+                product_translation.name.as_deref().unwrap_or_default(),
+                product_translation.name_sufix.unwrap_or_default()
+            );
+            let description = product_domain.description.unwrap_or_default(); // nutnost použití take() bylo tímto "Error - Borrow of partially moved value: 'product'"
+            let short_description = product_domain.short_description.unwrap_or_default();
 
             results.insert(
                 product.id,
                 ProductExportData {
                     id: product.id,
                     catnum: product.catnum.clone(),
-                    partno: product.partno.take().unwrap_or_default(),
-                    // take() vezme hodnotu z Option a nahradi za ni None
-                    // tim padem neni "partno" uninitialized (neni zde move) a move semantic je pak ok
-                    ean: product.ean.take().unwrap_or_default(),
+                    partno: partno.clone(),
+                    ean: ean.clone(),
                     name: product_translation
                         .name
                         .as_ref()
                         .unwrap_or(&"".to_string())
                         .clone(),
-                    fullname: format!(
-                        "{} {} {}",
-                        product_translation.name_prefix.unwrap_or_default(),
-                        product_translation.name.unwrap_or_default(),
-                        product_translation.name_sufix.unwrap_or_default()
-                    ),
-                    description: product_domain.description.unwrap_or_default(), // nutnost použití take() bylo tímto "Error - Borrow of partially moved value: 'product'"
-                    short_description: product_domain.short_description.unwrap_or_default(),
-                    brand: product.brand_id.map_or("".to_string(), |i| i.to_string()),
+                    full_name: full_name.clone(),
+                    description: description.clone(),
+                    short_description: short_description.clone(),
+                    brand: product.brand_id,
                     flags: flag_ids,
                     categories: category_ids,
+                    slug: slug.unwrap_or_default(),
+                    image_url,
+                    availability,
+                    in_stock,
+                    prices,
+                    parameters,
+                    variants: variants.iter().map(|variant| variant.id).collect(),
+                    searching_names: full_name,
+                    searching_catnums: product.catnum,
+                    searching_partnos: partno,
+                    searching_eans: ean,
+                    searching_short_descriptions: short_description,
+                    searching_descriptions: description,
                 },
             );
             // dbg!(results);
             // panic!();
         }
 
-        results
+        Ok(results)
     }
-    ///
-    /// Tato verze, kde rovnou joinuju product_translations je mega pomala, cca 0.8s jeden dotaz
-    /// - zpusobil to proste ten join
-    /// Zatímco když jej dělám odděleně, byť pro každý produkt zvlášť, tak to frčí rychle.
-    ///
+
+    // Tato verze, kde rovnou joinuju product_translations je mega pomala, cca 0.8s jeden dotaz
+    // - zpusobil to proste ten join
+    // Zatímco když jej dělám odděleně, byť pro každý produkt zvlášť, tak to frčí rychle.
+    //
     // async fn get_products_data_macro_as_sloooow(
     //     &self,
     //     domain_id: u8,
@@ -167,12 +252,14 @@ impl ProductIndex {
     //     .unwrap_or_default()
     // }
 
+    #[tracing::instrument(name = "products_query", level = "debug", skip(self, conn))]
     async fn get_products_data_macro_as(
         &self,
+        conn: &mut PgConnection,
         domain_id: u8,
         last_processed_id: u32,
         batch_size: u32,
-    ) -> Vec<ProductForElasticExport> {
+    ) -> Result<Vec<ProductForElasticExport>, sqlx::Error> {
         sqlx::query_as!(
             ProductForElasticExport,
             r#"
@@ -189,9 +276,8 @@ impl ProductIndex {
             last_processed_id as i32,
             batch_size as i32
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
         .await
-        .unwrap_or_default()
     }
 
     // async fn get_products_data_macro(
@@ -224,8 +310,10 @@ impl ProductIndex {
     //     // tohle uz mi hezky vraci anonymous Struct s vysledkama, sqlx samo automaticky namapuje typy, pak uz je na me jak si to dal pouziju
     // }
 
+    #[allow(dead_code)]
     async fn get_products_data(
         &self,
+        conn: &mut PgConnection,
         domain_id: u8,
         last_processed_id: u32,
         batch_size: u32,
@@ -243,7 +331,7 @@ impl ProductIndex {
         .bind(last_processed_id as i32)
         .bind(last_processed_id as i32)
         .bind(batch_size as i32)
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
         .await
         .unwrap_or_default()
     }
@@ -285,33 +373,46 @@ impl ProductIndex {
     //     products
     // }
 
+    #[tracing::instrument(name = "product_translation_query", level = "debug", skip(self, conn))]
     pub async fn get_product_translation(
         &self,
+        conn: &mut PgConnection,
         product_id: i32,
-    ) -> ProductTranslationForElasticExport {
-        sqlx::query_as!(
+        locale: &str,
+    ) -> Result<ProductTranslationForElasticExport, sqlx::Error> {
+        let product_translation = sqlx::query_as!(
             ProductTranslationForElasticExport,
             r#"SELECT name, name_prefix, name_sufix
             FROM product_translations
-            WHERE translatable_id = $1 AND locale = 'cs'"#,
-            product_id
+            WHERE translatable_id = $1 AND locale = $2"#,
+            product_id,
+            locale
         )
-        .fetch_one(&self.pool)
-        .await
-        .unwrap_or_default()
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(product_translation.unwrap_or_default())
     }
 
-    pub async fn get_product_domain(&self, product_id: i32) -> ProductDomainForElasticExport {
-        sqlx::query_as!(
+    #[tracing::instrument(name = "product_domain_query", level = "debug", skip(self, conn))]
+    pub async fn get_product_domain(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+        domain_id: u8,
+    ) -> Result<ProductDomainForElasticExport, sqlx::Error> {
+        let product_domain = sqlx::query_as!(
             ProductDomainForElasticExport,
             r#"SELECT id, domain_id, description, short_description
             FROM product_domains
-            WHERE product_id = $1 AND domain_id = 1"#,
-            product_id
+            WHERE product_id = $1 AND domain_id = $2"#,
+            product_id,
+            domain_id as i32
         )
-        .fetch_one(&self.pool)
-        .await
-        .unwrap_or_default()
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(product_domain.unwrap_or_default())
     }
 
     // V php musi udelat select * a vytahnout jen "idcka"
@@ -319,8 +420,14 @@ impl ProductIndex {
     // FROM flags t0
     // INNER JOIN product_domain_flags ON t0.id = product_domain_flags.flag_id
     // WHERE product_domain_flags.product_domain_id = $1
-    pub async fn extract_flags_for_domain(&self, product_ids: Vec<i32>, domain_id: u8) -> Vec<i32> {
-        sqlx::query!(
+    #[tracing::instrument(name = "product_flags_query", level = "debug", skip(self, conn))]
+    pub async fn extract_flags_for_domain(
+        &self,
+        conn: &mut PgConnection,
+        product_ids: Vec<i32>,
+        domain_id: u8,
+    ) -> Result<Vec<i32>, sqlx::Error> {
+        let rows = sqlx::query!(
             r#"
             SELECT DISTINCT(pdf.flag_id)
             FROM product_domains pd
@@ -330,20 +437,20 @@ impl ProductIndex {
             &product_ids,
             domain_id as i32
         )
-        .fetch_all(&self.pool)
-        .await
-        .unwrap()
-        .iter()
-        .map(|row| row.flag_id)
-        .collect()
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.flag_id).collect())
     }
 
+    #[tracing::instrument(name = "product_variants_query", level = "debug", skip(self, conn))]
     async fn get_sellable_variants(
         &self,
+        conn: &mut PgConnection,
         main_variant_id: i32,
         domain_id: u8,
         pricing_group_id: i32,
-    ) -> Vec<ProductForElasticExport> {
+    ) -> Result<Vec<ProductForElasticExport>, sqlx::Error> {
         sqlx::query_as!(
             ProductForElasticExport,
             r#"SELECT p.id, p.catnum, p.partno, p.ean, p.brand_id
@@ -362,13 +469,13 @@ impl ProductIndex {
             "main",
             main_variant_id
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
         .await
-        .unwrap_or_default()
     }
 
     pub async fn get_product_main_category_by_domain_id(
         &self,
+        conn: &mut PgConnection,
         product_id: i32,
         domain_id: u8,
     ) -> MainCategory {
@@ -388,13 +495,19 @@ impl ProductIndex {
             domain_id as i32,
             domain_id as i32
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await
         .unwrap_or_default()
     }
 
-    pub async fn get_category_ids(&self, product_id: i32, domain_id: u8) -> Vec<i32> {
-        sqlx::query!(
+    #[tracing::instrument(name = "product_categories_query", level = "debug", skip(self, conn))]
+    pub async fn get_category_ids(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+        domain_id: u8,
+    ) -> Result<Vec<i32>, sqlx::Error> {
+        let rows = sqlx::query!(
             r#"
             SELECT category_id 
             FROM product_category_domains 
@@ -403,12 +516,171 @@ impl ProductIndex {
             product_id,
             domain_id as i32
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.category_id).collect())
+    }
+
+    #[tracing::instrument(name = "product_slug_query", level = "debug", skip(self, conn))]
+    pub async fn get_slug(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+        domain_id: u8,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT slug
+            FROM friendly_urls
+            WHERE domain_id = $1 AND entity_id = $2 AND route_name = $3 AND main = TRUE
+            "#,
+            domain_id as i32,
+            product_id,
+            PRODUCT_ROUTE_NAME
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    /**
+     * The first image of the product by position, as ImageFacade::getImageByEntity() takes it
+     */
+    #[tracing::instrument(name = "product_image_query", level = "debug", skip(self, conn))]
+    pub async fn get_image_url(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let image = sqlx::query!(
+            r#"
+            SELECT id, extension
+            FROM images
+            WHERE entity_name = 'product' AND entity_id = $1
+            ORDER BY position NULLS LAST, id
+            LIMIT 1
+            "#,
+            product_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(image.map(|image| {
+            format!(
+                "/content/images/product/original/{}.{}",
+                image.id, image.extension
+            )
+        }))
+    }
+
+    /**
+     * Name of the calculated availability, as ProductExportRepository::extractResult() takes it
+     */
+    #[tracing::instrument(name = "product_availability_query", level = "debug", skip(self, conn))]
+    pub async fn get_availability(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+        locale: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT at.name
+            FROM products p
+            INNER JOIN availability_translations at ON at.translatable_id = p.calculated_availability_id
+            WHERE p.id = $1 AND at.locale = $2
+            "#,
+            product_id,
+            locale
+        )
+        .fetch_optional(&mut *conn)
+        .await
+    }
+
+    /**
+     * Whether any of the products (the product and its sellable variants) has an availability with zero dispatch time
+     */
+    #[tracing::instrument(name = "product_stock_query", level = "debug", skip(self, conn))]
+    pub async fn is_in_stock(
+        &self,
+        conn: &mut PgConnection,
+        product_ids: &[i32],
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM products p
+                INNER JOIN availabilities a ON a.id = p.calculated_availability_id
+                WHERE p.id = ANY ($1) AND a.dispatch_time = 0
+            ) AS "in_stock!"
+            "#,
+            product_ids
+        )
+        .fetch_one(&mut *conn)
+        .await
+    }
+
+    /**
+     * Prices of all pricing groups of the domain, a main variant has the price of its cheapest variant already.
+     * Filtering prices span the product_ids (the product and its sellable variants).
+     */
+    #[tracing::instrument(name = "product_prices_query", level = "debug", skip(self, conn))]
+    pub async fn get_prices(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+        product_ids: &[i32],
+        domain_id: u8,
+    ) -> Result<Vec<ProductPriceExportData>, sqlx::Error> {
+        sqlx::query_as!(
+            ProductPriceExportData,
+            r#"
+            SELECT pcp.pricing_group_id, pcp.price_with_vat::FLOAT8 AS "price_with_vat!",
+                MIN(fp.price_with_vat)::FLOAT8 AS "filtering_minimal_price!",
+                MAX(fp.price_with_vat)::FLOAT8 AS "filtering_maximal_price!"
+            FROM product_calculated_prices pcp
+            INNER JOIN pricing_groups pg ON pg.id = pcp.pricing_group_id
+            INNER JOIN product_calculated_prices fp ON fp.pricing_group_id = pcp.pricing_group_id
+                AND fp.product_id = ANY ($2) AND fp.price_with_vat IS NOT NULL
+            WHERE pcp.product_id = $1 AND pg.domain_id = $3 AND pcp.price_with_vat IS NOT NULL
+            GROUP BY pcp.pricing_group_id, pcp.price_with_vat
+            ORDER BY pcp.pricing_group_id
+            "#,
+            product_id,
+            product_ids,
+            domain_id as i32
+        )
+        .fetch_all(&mut *conn)
+        .await
+    }
+
+    #[tracing::instrument(name = "product_parameters_query", level = "debug", skip(self, conn))]
+    pub async fn get_parameters(
+        &self,
+        conn: &mut PgConnection,
+        product_id: i32,
+        locale: &str,
+    ) -> Result<Vec<ProductParameterExportData>, sqlx::Error> {
+        sqlx::query_as!(
+            ProductParameterExportData,
+            r#"
+            SELECT p.id AS parameter_id, p.uuid::TEXT AS "parameter_uuid!", pt.name AS parameter_name,
+                ut.name AS "parameter_unit?", pv.id AS parameter_value_id,
+                pv.uuid::TEXT AS "parameter_value_uuid!", pv.text AS parameter_value_text
+            FROM product_parameter_values ppv
+            INNER JOIN parameters p ON p.id = ppv.parameter_id
+            INNER JOIN parameter_translations pt ON pt.translatable_id = p.id AND pt.locale = $2
+            INNER JOIN parameter_values pv ON pv.id = ppv.value_id AND pv.locale = $2
+            LEFT JOIN unit_translations ut ON ut.translatable_id = p.unit_id AND ut.locale = $2
+            WHERE ppv.product_id = $1
+            ORDER BY p.id, pv.id
+            "#,
+            product_id,
+            locale
+        )
+        .fetch_all(&mut *conn)
         .await
-        .unwrap()
-        .iter()
-        .map(|row| row.category_id)
-        .collect()
     }
 
     // category_ids = extractCategories
@@ -450,3 +722,161 @@ impl ProductIndex {
     // ORDER BY c.level DESC, c.lft ASC
     // LIMIT 1;
 }
+
+impl Index for ProductIndex {
+    type Document = ProductExportData;
+
+    fn name() -> &'static str {
+        "product"
+    }
+
+    /**
+     * ProductIndex::getTotalCount(int $domainId): int
+     */
+    async fn get_total_count(&self, domain_id: u8) -> Result<i64, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let total_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT p.id) AS "total_count!"
+            FROM products p
+            INNER JOIN product_visibilities pv ON p.id = pv.product_id
+            WHERE pv.domain_id = $1 AND pv.visible = TRUE
+            "#,
+            domain_id as i32
+        )
+        .fetch_one(&mut *transaction)
+        .instrument(tracing::debug_span!("product_count_query", domain_id))
+        .await
+        .map_err(ElasticError::Postgres)?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(total_count)
+    }
+
+    async fn get_export_data_for_batch(
+        &self,
+        domain_id: u8,
+        last_processed_id: u32,
+        batch_size: u32,
+    ) -> Result<BTreeMap<i32, ProductExportData>, ElasticError> {
+        // self.get_products_data_macro(domain_id, last_processed_id, batch_size)
+        //     .await;
+        // let products = self
+        //     .get_products_data(domain_id, last_processed_id, batch_size)
+        //     .await;
+
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let domain = self.export_source.get_domain(domain_id)?;
+        let products = self
+            .get_products_data_macro_as(&mut transaction, domain_id, last_processed_id, batch_size)
+            .await
+            .map_err(ElasticError::Postgres)?;
+
+        let results = self
+            .get_export_data_for_products(&mut transaction, products, domain)
+            .await
+            .map_err(ElasticError::Postgres)?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(results)
+    }
+
+    /**
+     * ProductIndex::getExportDataForIds(int $domainId, array $restrictToIds): array
+     */
+    async fn get_export_data_for_ids(
+        &self,
+        domain_id: u8,
+        ids: Vec<i32>,
+    ) -> Result<BTreeMap<i32, ProductExportData>, ElasticError> {
+        let mut transaction = self.export_source.begin_transaction().await?;
+        let domain = self.export_source.get_domain(domain_id)?;
+        let products = sqlx::query_as!(
+            ProductForElasticExport,
+            r#"
+            SELECT p.id, p.catnum, p.partno, p.ean, p.brand_id
+            FROM products p
+            INNER JOIN product_visibilities pv ON p.id = pv.product_id
+            WHERE pv.domain_id = $1 AND pv.visible = TRUE AND p.id = ANY ($2)
+            GROUP BY p.id
+            ORDER BY p.id
+            "#,
+            domain_id as i32,
+            &ids
+        )
+        .fetch_all(&mut *transaction)
+        .instrument(tracing::debug_span!(
+            "products_query",
+            domain_id,
+            id_count = ids.len()
+        ))
+        .await
+        .map_err(ElasticError::Postgres)?;
+
+        let results = self
+            .get_export_data_for_products(&mut transaction, products, domain)
+            .await
+            .map_err(ElasticError::Postgres)?;
+        transaction.commit().await.map_err(ElasticError::Postgres)?;
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elastic::mapping_validator::MappingValidator;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn export_data_matches_mapping() {
+        let definition: Value =
+            serde_json::from_str(include_str!("../resources/definition/product/1.json")).unwrap();
+        let export_data = ProductExportData {
+            id: 1,
+            catnum: "9177759".to_string(),
+            partno: "SLE 571 X".to_string(),
+            ean: "8845781245931".to_string(),
+            name: "Kolo".to_string(),
+            full_name: "Horské kolo".to_string(),
+            description: "<p>Popis</p>".to_string(),
+            short_description: "Krátký popis".to_string(),
+            brand: Some(12),
+            flags: vec![1, 2],
+            categories: vec![3],
+            slug: "horske-kolo".to_string(),
+            image_url: Some("/content/images/product/original/5.jpg".to_string()),
+            availability: Some("Skladem".to_string()),
+            in_stock: true,
+            prices: vec![ProductPriceExportData {
+                pricing_group_id: 1,
+                price_with_vat: 12100.0,
+                filtering_minimal_price: 9900.0,
+                filtering_maximal_price: 12100.0,
+            }],
+            parameters: vec![ProductParameterExportData {
+                parameter_id: 7,
+                parameter_uuid: "0b4b4c3e-0000-4000-8000-000000000007".to_string(),
+                parameter_name: "Barva".to_string(),
+                parameter_unit: None,
+                parameter_value_id: 70,
+                parameter_value_uuid: "0b4b4c3e-0000-4000-8000-000000000070".to_string(),
+                parameter_value_text: "červená".to_string(),
+            }],
+            variants: vec![2, 3],
+            searching_names: "Horské kolo".to_string(),
+            searching_catnums: "9177759".to_string(),
+            searching_partnos: "SLE 571 X".to_string(),
+            searching_eans: "8845781245931".to_string(),
+            searching_short_descriptions: "Krátký popis".to_string(),
+            searching_descriptions: "<p>Popis</p>".to_string(),
+        };
+
+        let violations = MappingValidator::from_definition(&definition)
+            .unwrap()
+            .validate(&json!(export_data));
+
+        assert_eq!(violations, vec![]);
+    }
+}
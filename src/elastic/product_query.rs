@@ -0,0 +1,337 @@
+use super::product_filter::ProductFilterData;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/**
+ * Analyzed fields of the product index and their boosts, the same set as Shopsys FilterQuery::search() uses
+ */
+const SEARCH_FIELDS: [&str; 13] = [
+    "searching_names.full_with_diacritic^60",
+    "searching_names.full_without_diacritic^50",
+    "searching_names^45",
+    "searching_names.edge_ngram_with_diacritic^40",
+    "searching_names.edge_ngram_without_diacritic^35",
+    "searching_catnums^50",
+    "searching_catnums.edge_ngram_unanalyzed_words^25",
+    "searching_partnos^40",
+    "searching_partnos.edge_ngram_unanalyzed_words^20",
+    "searching_eans^60",
+    "searching_eans.edge_ngram_unanalyzed_words^30",
+    "searching_short_descriptions^5",
+    "searching_descriptions^5",
+];
+
+/**
+ * max_result_window of the product index definition, from + size of a search must not exceed it
+ */
+pub const MAX_RESULT_WINDOW: u32 = 30000;
+
+/**
+ * Shopsys ProductListOrderingConfig ordering modes
+ */
+pub const ORDER_BY_PRIORITY: &str = "priority";
+pub const ORDER_BY_NAME_ASC: &str = "name_asc";
+pub const ORDER_BY_NAME_DESC: &str = "name_desc";
+pub const ORDER_BY_PRICE_ASC: &str = "price_asc";
+pub const ORDER_BY_PRICE_DESC: &str = "price_desc";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        }
+    }
+}
+
+/**
+ * Shopsys FilterQuery - builds the request body for searching in the product index
+ *
+ * let body = ProductQuery::new()
+ *     .search_text("kolo")
+ *     .filter_by_brands(vec![1, 2])
+ *     .order_by_price(SortDirection::Asc, 1)
+ *     .paginate(2, 20)
+ *     .get_query();
+ */
+#[derive(Debug, Clone)]
+pub struct ProductQuery {
+    text: Option<String>,
+    filter_data: ProductFilterData,
+    sorting: Vec<Value>,
+    from: u32,
+    size: u32,
+    with_aggregations: bool,
+}
+
+impl Default for ProductQuery {
+    fn default() -> Self {
+        ProductQuery {
+            text: None,
+            filter_data: ProductFilterData::default(),
+            sorting: vec![],
+            from: 0,
+            size: 1000,
+            with_aggregations: false,
+        }
+    }
+}
+
+impl ProductQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn search_text(mut self, text: &str) -> Self {
+        self.text = match text.trim() {
+            "" => None,
+            text => Some(text.to_string()),
+        };
+        self
+    }
+
+    /**
+     * Replaces all filters at once, e.g. by the ones selected in the product list filter form
+     */
+    pub fn apply_filter_data(mut self, filter_data: &ProductFilterData) -> Self {
+        self.filter_data = filter_data.clone();
+        self
+    }
+
+    pub fn filter_by_brands(mut self, brand_ids: Vec<i32>) -> Self {
+        self.filter_data.brands = brand_ids;
+        self
+    }
+
+    pub fn filter_by_flags(mut self, flag_ids: Vec<i32>) -> Self {
+        self.filter_data.flags = flag_ids;
+        self
+    }
+
+    pub fn filter_by_categories(mut self, category_ids: Vec<i32>) -> Self {
+        self.filter_data.categories = category_ids;
+        self
+    }
+
+    /**
+     * Products with a price of the pricing group between the limits, a missing limit is not checked
+     */
+    pub fn filter_by_price_range(
+        mut self,
+        minimal_price: Option<f64>,
+        maximal_price: Option<f64>,
+        pricing_group_id: i32,
+    ) -> Self {
+        self.filter_data.minimal_price = minimal_price;
+        self.filter_data.maximal_price = maximal_price;
+        self.filter_data.pricing_group_id = pricing_group_id;
+        self
+    }
+
+    /**
+     * parameter_id => parameter_value_ids, a product needs one of the values of every parameter
+     */
+    pub fn filter_by_parameters(mut self, parameters: BTreeMap<i32, Vec<i32>>) -> Self {
+        self.filter_data.parameters = parameters;
+        self
+    }
+
+    pub fn filter_only_in_stock(mut self) -> Self {
+        self.filter_data.in_stock = true;
+        self
+    }
+
+    pub fn order_by_price(mut self, direction: SortDirection, pricing_group_id: i32) -> Self {
+        self.sorting = vec![json!({
+            "prices.price_with_vat": {
+                "order": direction.as_str(),
+                "nested": {
+                    "path": "prices",
+                    "filter": { "term": { "prices.pricing_group_id": pricing_group_id } },
+                },
+            }
+        })];
+        self
+    }
+
+    pub fn order_by_name(mut self, direction: SortDirection) -> Self {
+        self.sorting = vec![json!({ "name.keyword": direction.as_str() })];
+        self
+    }
+
+    /**
+     * ordering_priority is not exported yet, so the most relevant products go first and then by name
+     */
+    pub fn order_by_priority(mut self) -> Self {
+        self.sorting = vec![
+            json!({ "_score": "desc" }),
+            json!({ "name.keyword": "asc" }),
+        ];
+        self
+    }
+
+    /**
+     * Unknown ordering mode falls back to ordering by priority
+     */
+    pub fn apply_ordering(self, ordering_mode_id: &str, pricing_group_id: i32) -> Self {
+        match ordering_mode_id {
+            ORDER_BY_NAME_ASC => self.order_by_name(SortDirection::Asc),
+            ORDER_BY_NAME_DESC => self.order_by_name(SortDirection::Desc),
+            ORDER_BY_PRICE_ASC => self.order_by_price(SortDirection::Asc, pricing_group_id),
+            ORDER_BY_PRICE_DESC => self.order_by_price(SortDirection::Desc, pricing_group_id),
+            _ => self.order_by_priority(),
+        }
+    }
+
+    /**
+     * Page is counted from 1, pages behind MAX_RESULT_WINDOW are empty
+     */
+    pub fn paginate(mut self, page: u32, limit: u32) -> Self {
+        self.from = (page.max(1) - 1)
+            .saturating_mul(limit)
+            .min(MAX_RESULT_WINDOW);
+        self.size = limit.min(MAX_RESULT_WINDOW - self.from);
+        self
+    }
+
+    /**
+     * Adds facet counts of the filter, selected filters are then applied as "post_filter"
+     */
+    pub fn with_aggregations(mut self) -> Self {
+        self.with_aggregations = true;
+        self
+    }
+
+    pub fn get_query(&self) -> Value {
+        let query = match &self.text {
+            None => json!({ "match_all": {} }),
+            Some(text) => json!({
+                "multi_match": {
+                    "query": text,
+                    "fields": SEARCH_FIELDS,
+                }
+            }),
+        };
+
+        let mut body = json!({
+            "from": self.from,
+            "size": self.size,
+            "track_total_hits": true,
+        });
+
+        if self.with_aggregations {
+            body["query"] = query;
+            body["post_filter"] = self.filter_data.get_post_filter();
+            body["aggs"] = self.filter_data.get_aggregations();
+        } else {
+            body["query"] = json!({
+                "bool": {
+                    "must": query,
+                    "filter": self.filter_data.get_post_filter(),
+                }
+            });
+        }
+
+        if !self.sorting.is_empty() {
+            body["sort"] = json!(self.sorting);
+        }
+
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_json_snapshot;
+
+    #[test]
+    fn empty_query() {
+        assert_json_snapshot!(ProductQuery::new().get_query());
+    }
+
+    #[test]
+    fn search_text_query() {
+        assert_json_snapshot!(ProductQuery::new()
+            .search_text("kolo")
+            .paginate(2, 20)
+            .get_query());
+    }
+
+    #[test]
+    fn filtered_query() {
+        assert_json_snapshot!(ProductQuery::new()
+            .search_text("kolo")
+            .filter_by_brands(vec![1, 2])
+            .filter_by_flags(vec![3])
+            .filter_by_categories(vec![4, 5])
+            .filter_by_price_range(Some(100.0), Some(2500.5), 1)
+            .filter_only_in_stock()
+            .filter_by_parameters(BTreeMap::from([(7, vec![70, 71])]))
+            .order_by_priority()
+            .get_query());
+    }
+
+    #[test]
+    fn price_range_without_maximum_query() {
+        assert_json_snapshot!(ProductQuery::new()
+            .filter_by_price_range(Some(100.0), None, 2)
+            .get_query());
+    }
+
+    #[test]
+    fn ordered_by_price_query() {
+        assert_json_snapshot!(ProductQuery::new()
+            .filter_by_categories(vec![4])
+            .order_by_price(SortDirection::Desc, 2)
+            .paginate(1, 12)
+            .get_query());
+    }
+
+    #[test]
+    fn ordered_by_name_query() {
+        assert_json_snapshot!(ProductQuery::new()
+            .order_by_name(SortDirection::Asc)
+            .get_query());
+    }
+
+    #[test]
+    fn paginate_stays_in_max_result_window() {
+        let query = ProductQuery::new().paginate(1499, 20).get_query();
+        assert_eq!(
+            (query["from"].clone(), query["size"].clone()),
+            (json!(29960), json!(20))
+        );
+
+        let query = ProductQuery::new().paginate(1501, 20).get_query();
+        assert_eq!(
+            (query["from"].clone(), query["size"].clone()),
+            (json!(30000), json!(0))
+        );
+
+        let query = ProductQuery::new().paginate(u32::MAX, u32::MAX).get_query();
+        assert_eq!(
+            (query["from"].clone(), query["size"].clone()),
+            (json!(30000), json!(0))
+        );
+    }
+
+    #[test]
+    fn query_with_aggregations() {
+        assert_json_snapshot!(ProductQuery::new()
+            .search_text("kolo")
+            .filter_by_brands(vec![1])
+            .filter_by_flags(vec![3])
+            .filter_by_price_range(None, Some(2500.0), 1)
+            .filter_by_parameters(BTreeMap::from([(7, vec![70])]))
+            .with_aggregations()
+            .paginate(1, 20)
+            .get_query());
+    }
+}
@@ -0,0 +1,130 @@
+use super::error::ElasticError;
+use super::index_definition::IndexDefinition;
+use super::index_repository::IndexRepository;
+use super::product_filter::{ProductFilterCountData, ProductFilterData};
+use super::product_query::ProductQuery;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const PRODUCTS_PER_PAGE: u32 = 20;
+
+#[derive(Debug, Serialize)]
+pub struct ProductSearchResult {
+    pub hits: Vec<Value>,
+    pub total: u64,
+    pub took: u64,
+    pub aggregations: ProductFilterCountData,
+}
+
+/**
+ * Product data needed for rendering a product in lists, autocomplete etc.
+ */
+#[derive(Debug, Serialize)]
+pub struct ProductListItem {
+    pub id: i64,
+    pub name: String,
+    pub full_name: String,
+    pub catnum: String,
+    pub detail_url: String,
+    pub image_url: Option<String>,
+    /// price with VAT of the pricing group of the domain
+    pub price: Option<f64>,
+}
+
+pub struct ProductSearch<'a> {
+    index_repository: &'a IndexRepository,
+}
+
+impl<'a> ProductSearch<'a> {
+    pub fn new(index_repository: &'a IndexRepository) -> Self {
+        ProductSearch { index_repository }
+    }
+
+    /**
+     * Full-text search in the current product alias narrowed by the filter, page is counted from 1
+     */
+    pub async fn search(
+        &self,
+        index_definition: &IndexDefinition,
+        text: &str,
+        filter_data: &ProductFilterData,
+        page: u32,
+    ) -> Result<ProductSearchResult, ElasticError> {
+        self.search_by_query(
+            index_definition,
+            &ProductQuery::new()
+                .search_text(text)
+                .apply_filter_data(filter_data)
+                .with_aggregations()
+                .paginate(page, PRODUCTS_PER_PAGE),
+        )
+        .await
+    }
+
+    pub async fn search_by_query(
+        &self,
+        index_definition: &IndexDefinition,
+        product_query: &ProductQuery,
+    ) -> Result<ProductSearchResult, ElasticError> {
+        let response_body = self
+            .index_repository
+            .search(
+                &index_definition.get_index_alias(),
+                product_query.get_query(),
+            )
+            .await?;
+
+        Ok(ProductSearchResult::from_response_body(&response_body))
+    }
+}
+
+impl ProductSearchResult {
+    pub fn from_response_body(response_body: &Value) -> Self {
+        ProductSearchResult {
+            hits: get_sources(response_body).cloned().collect(),
+            total: response_body["hits"]["total"]["value"]
+                .as_u64()
+                .unwrap_or_default(),
+            took: response_body["took"].as_u64().unwrap_or_default(),
+            aggregations: ProductFilterCountData::from_aggregations(&response_body["aggregations"]),
+        }
+    }
+}
+
+impl ProductListItem {
+    /**
+     * Only fields exported by ProductIndex are read, the detail is reachable by the product id
+     */
+    pub fn from_source(source: &Value, pricing_group_id: i32) -> Self {
+        let id = source["id"].as_i64().unwrap_or_default();
+        ProductListItem {
+            id,
+            name: source["name"].as_str().unwrap_or_default().to_string(),
+            full_name: source["full_name"].as_str().unwrap_or_default().to_string(),
+            catnum: source["catnum"].as_str().unwrap_or_default().to_string(),
+            detail_url: format!("/product/{id}"),
+            image_url: source["image_url"].as_str().map(str::to_string),
+            price: get_price_with_vat(source, pricing_group_id),
+        }
+    }
+}
+
+/**
+ * Price of the product document in the given pricing group
+ */
+pub fn get_price_with_vat(source: &Value, pricing_group_id: i32) -> Option<f64> {
+    source["prices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|price| price["pricing_group_id"].as_i64() == Some(pricing_group_id as i64))
+        .and_then(|price| price["price_with_vat"].as_f64())
+}
+
+pub fn get_sources(response_body: &Value) -> impl Iterator<Item = &Value> {
+    response_body["hits"]["hits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|hit| &hit["_source"])
+}
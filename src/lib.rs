@@ -0,0 +1,8 @@
+pub mod category;
+pub mod domain;
+pub mod elastic;
+pub mod metrics;
+pub mod postgres;
+pub mod product;
+pub mod settings;
+pub mod web;
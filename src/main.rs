@@ -1,30 +1,22 @@
 #![allow(dead_code, unused)]
 
-mod elastic;
-mod postgres;
-mod product;
 mod utils;
 
-use crate::elastic::index_facade::IndexFacade;
-use crate::elastic::index_repository::IndexRepository;
-use crate::elastic::product_index::{
-    ProductDomainForElasticExport, ProductForElasticExport, ProductIndex,
-};
-use crate::postgres::postgres_connect;
-use crate::product::product_repository;
 use dotenvy::dotenv;
-use elasticsearch::cat::CatIndicesParts;
-use elasticsearch::indices::{
-    IndicesCreateParts, IndicesExistsParts, IndicesGetAliasParts, IndicesPutAliasParts,
+use shopsys_sqlx_test::elastic::index_definition_loader::IndexDefinitionLoader;
+use shopsys_sqlx_test::elastic::index_repository::IndexRepository;
+use shopsys_sqlx_test::elastic::product_index::{
+    ProductDomainForElasticExport, ProductForElasticExport,
 };
-use elasticsearch::Elasticsearch;
-use serde_json::Value;
-use sqlx::{Connection, FromRow, Pool, Postgres, Row};
-use std::collections::HashMap;
-use std::env;
-use std::error::Error;
-use std::fmt;
-use tokio::fs;
+use shopsys_sqlx_test::postgres::postgres_config;
+use shopsys_sqlx_test::postgres::postgres_connect;
+use shopsys_sqlx_test::settings::settings::Settings;
+use shopsys_sqlx_test::web;
+use shopsys_sqlx_test::web::app_state::AppState;
+use shopsys_sqlx_test::web::HtmlTemplate;
+use sqlx::{Pool, Postgres};
+use std::future::IntoFuture;
+use std::sync::Arc;
 
 /*#[derive(Debug, FromRow)]
 struct SettingValue {
@@ -96,30 +88,102 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() {
-    dotenv().expect(".env file not found");
+    // .env is optional, settings can come from config/settings.toml or the real environment
+    dotenv().ok();
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "example_templates=debug".into()),
+                .unwrap_or_else(|_| "shopsys_sqlx_test=debug,tower_http=debug,sqlx=warn".into()),
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let settings =
+        Settings::load().unwrap_or_else(|err| panic!("Settings could not be loaded: {err}"));
+    settings
+        .validate_database()
+        .unwrap_or_else(|err| panic!("Settings could not be loaded: {err}"));
+
+    let state = AppState {
+        pools: postgres_connect::get_pools(
+            &settings.get_postgres_config(postgres_config::WEB_SERVER_PROFILE),
+        )
+        .await
+        .expect("Postgres pools could not be created"),
+        index_repository: IndexRepository::from_settings(&settings.elasticsearch)
+            .expect("Elasticsearch client could not be created"),
+        index_definition_loader: Arc::new(IndexDefinitionLoader::from_settings(
+            &settings.elasticsearch,
+        )),
+        settings: Arc::new(settings.clone()),
+    };
+
     // build our application with some routes
     let app = Router::new()
         .route("/greet/:name", get(greet))
-        .nest_service("/assets", tower_http::services::ServeDir::new("assets"));
+        .route(
+            "/api/products/search",
+            get(web::search_controller::search_products),
+        )
+        .route(
+            "/api/products/autocomplete",
+            get(web::search_controller::autocomplete_products),
+        )
+        .route(
+            "/api/articles/search",
+            get(web::search_controller::search_articles),
+        )
+        .route(
+            "/api/categories/search",
+            get(web::search_controller::search_categories),
+        )
+        .route(
+            "/api/categories/menu",
+            get(web::category_controller::category_menu),
+        )
+        .route(
+            "/category/:id",
+            get(web::category_controller::category_detail),
+        )
+        .route(
+            "/product/:slug_or_id",
+            get(web::product_controller::product_detail),
+        )
+        .nest_service("/assets", tower_http::services::ServeDir::new("assets"))
+        .layer(tower_http::trace::TraceLayer::new_for_http())
+        .with_state(state.clone());
+
+    // admin routes and metrics have no authentication, they are served only on the private admin address
+    let admin_app = Router::new()
+        .route("/admin/indexes", get(web::admin_controller::index_status))
+        .route("/metrics", get(web::metrics_controller::metrics))
+        .layer(tower_http::trace::TraceLayer::new_for_http())
+        .with_state(state);
 
     // run it
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let listener = tokio::net::TcpListener::bind(&settings.server.bind_address)
         .await
         .unwrap();
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+    let admin_listener = tokio::net::TcpListener::bind(&settings.server.admin_bind_address)
+        .await
+        .unwrap();
+    tracing::debug!(
+        "admin listening on {}",
+        admin_listener.local_addr().unwrap()
+    );
+    tokio::try_join!(
+        axum::serve(listener, app).into_future(),
+        axum::serve(admin_listener, admin_app).into_future()
+    )
+    .unwrap();
 }
 
-async fn greet(extract::Path(name): extract::Path<String>) -> impl IntoResponse {
-    let pool = postgres::postgres_connect::get_pool().await;
+async fn greet(
+    extract::State(state): extract::State<AppState>,
+    extract::Path(name): extract::Path<String>,
+) -> impl IntoResponse {
+    let pool = state.pools.read().clone();
     let products = get_products(pool.clone(), 1, 1, 10000).await;
     let mut products_for_template = vec![];
     for product in products {
@@ -148,24 +212,6 @@ struct HelloTemplate {
     products: Vec<ProductForTemplate>,
 }
 
-struct HtmlTemplate<T>(T);
-
-impl<T> IntoResponse for HtmlTemplate<T>
-where
-    T: Template,
-{
-    fn into_response(self) -> Response {
-        match self.0.render() {
-            Ok(html) => Html(html).into_response(),
-            Err(err) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to render template. Error: {err}"),
-            )
-                .into_response(),
-        }
-    }
-}
-
 async fn get_products(
     pool: Pool<Postgres>,
     domain_id: u8,
@@ -208,122 +254,3 @@ pub async fn get_product_domain(
     .await
     .unwrap_or_default()
 }
-
-#[tokio::main]
-async fn main2() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().expect(".env file not found");
-    let shopsys_elasticsearch_structure_dir = "./src/resources/definition/";
-
-    let pool = postgres::postgres_connect::get_pool().await;
-    // let products = product_repository::get_products(&pool, 2).await;
-    // println!("\n==== products: \n{:#?}", products);
-
-    // ---------------------------------------------------------------------------------------------
-    // ELASTICSEARCH PART
-    // ---------------------------------------------------------------------------------------------
-    let index_definition_loader = elastic::index_definition_loader::IndexDefinitionLoader::new(
-        shopsys_elasticsearch_structure_dir.to_string(),
-        env::var("ELASTIC_SEARCH_INDEX_PREFIX").unwrap(),
-    );
-
-    // product definition
-    let index_definition = index_definition_loader.get_definition("product".to_string(), 1);
-
-    let index_repository = IndexRepository {
-        client: Elasticsearch::default(),
-    };
-
-    let product_index = ProductIndex::new(pool.clone());
-    let index_facade = IndexFacade::new(&index_repository);
-
-    index_facade.export(&product_index, &index_definition).await;
-    // index_facade.migrate(&index_definition).await;
-
-    return Ok(());
-
-    // index_repository
-    //     .get_all_indicies_by_cat_api_and_print_them()
-    //     .await;
-    // return Ok(());
-
-    // ---------------------------------------------------------------------------------------------
-    // implementation of IndexRepository::deleteIndexByIndexDefinition 5.2.23
-    // ---------------------------------------------------------------------------------------------
-
-    println!(
-        "\n\nSearching for alias: {}",
-        index_definition.get_index_alias()
-    );
-
-    index_repository
-        .get_indicies_by_index_definition_raw(&index_definition)
-        .await;
-
-    println!("\n\n");
-
-    index_repository
-        .get_indicies_by_index_definition(&index_definition)
-        .await;
-
-    // panic!();
-
-    // TODO: refactor getAlias inside of delete_index_by... into standalone method and:
-    //   [x] try to get aliases directly from ES API with /_alias/{lunzo_product_1}
-    //      - done "get_indicies_by_index_definition"
-    //   [] or maybe using the /_cat/_aliases/ endpoint
-
-    // ---------------------------------------------------------------------------------------------
-
-    let index_name = "test_1_5e43a79979286148d59dee146ced642e";
-    println!(
-        "found index '{}': {:?}",
-        index_name,
-        index_repository.is_index_created(index_name).await
-    );
-
-    println!(
-        "found index alias '{}': {:?}",
-        index_definition.get_index_alias(),
-        index_repository
-            .is_alias_created(&index_definition.get_index_alias())
-            .await
-    );
-
-    if !index_repository.is_index_created(index_name).await {
-        // create index
-        let path = "./src/1.json";
-        let data_definition = fs::read_to_string(path).await?;
-        let json: Value = serde_json::from_str(&data_definition)?;
-        let res = index_repository
-            .client
-            .indices()
-            .create(IndicesCreateParts::Index(index_name))
-            .body(json)
-            .send()
-            .await?;
-        println!("created: {:?}", res.status_code());
-        println!("{:#?}", res.json::<Value>().await?);
-
-        // create alias
-        let res = index_repository
-            .client
-            .indices()
-            .put_alias(IndicesPutAliasParts::IndexName(&[index_name], "test_1"))
-            .send()
-            .await?;
-        println!("{:#?}", res.json::<Value>().await?);
-    }
-
-    let response = index_repository
-        .client
-        .indices()
-        .get_alias(IndicesGetAliasParts::Name(&[
-            &index_definition.get_index_alias()
-        ]))
-        .send()
-        .await?;
-
-    println!("{:?}", response.json::<Value>().await.unwrap());
-
-    Ok(())
-}
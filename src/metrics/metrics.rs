@@ -0,0 +1,88 @@
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+
+/**
+ * Registry of the process, the web server exposes it on /metrics and the console writes it to --metrics-file
+ */
+pub static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+/**
+ * Documents sent to Elasticsearch by the export, throughput is rate() of it
+ */
+pub static EXPORTED_DOCUMENTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "export_documents_total",
+            "Documents exported to Elasticsearch",
+        ),
+        &["index", "domain"],
+    ))
+});
+
+/**
+ * Duration of one export batch, phase is "postgres" for reading and "elasticsearch" for the bulk request
+ */
+pub static EXPORT_BATCH_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "export_batch_duration_seconds",
+            "Duration of reading and sending one export batch",
+        )
+        .buckets(exponential_buckets(0.01, 2.0, 12).unwrap()),
+        &["index", "phase"],
+    ))
+});
+
+/**
+ * Bulk requests that failed as a whole or in some of their items, labeled by the alias
+ */
+pub static BULK_FAILURES_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "elasticsearch_bulk_failures_total",
+            "Failed Elasticsearch bulk requests",
+        ),
+        &["index"],
+    ))
+});
+
+/**
+ * Latency of search requests, labeled by the alias they were sent to
+ */
+pub static SEARCH_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "elasticsearch_search_duration_seconds",
+            "Duration of Elasticsearch search requests",
+        )
+        .buckets(exponential_buckets(0.005, 2.0, 10).unwrap()),
+        &["index"],
+    ))
+});
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(
+    collector: Result<T, prometheus::Error>,
+) -> T {
+    let collector = collector.expect("Metric could not be created");
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("Metric could not be registered");
+    collector
+}
+
+/**
+ * All metrics in the Prometheus text format
+ */
+pub fn render() -> String {
+    // metrics are registered on first use, unused ones would be missing in the output
+    LazyLock::force(&EXPORTED_DOCUMENTS_TOTAL);
+    LazyLock::force(&EXPORT_BATCH_DURATION_SECONDS);
+    LazyLock::force(&BULK_FAILURES_TOTAL);
+    LazyLock::force(&SEARCH_DURATION_SECONDS);
+
+    TextEncoder::new()
+        .encode_to_string(&REGISTRY.gather())
+        .expect("Metrics could not be encoded")
+}
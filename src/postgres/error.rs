@@ -0,0 +1,21 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum PostgresError {
+    InvalidConfigValue(String),
+    Connection(sqlx::Error),
+}
+
+impl Error for PostgresError {}
+
+impl Display for PostgresError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostgresError::InvalidConfigValue(name) => {
+                write!(f, "Invalid value of Postgres config '{}'", name)
+            }
+            PostgresError::Connection(err) => write!(f, "Postgres connection failed: {}", err),
+        }
+    }
+}
@@ -1 +1,4 @@
+pub mod error;
+pub mod postgres_config;
 pub mod postgres_connect;
+pub mod snapshot;
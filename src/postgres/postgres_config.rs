@@ -0,0 +1,58 @@
+pub const WEB_SERVER_PROFILE: &str = "web";
+pub const EXPORTER_PROFILE: &str = "exporter";
+
+/**
+ * Pool options of one database connection, built by Settings::get_postgres_config for each profile
+ */
+#[derive(Debug, Clone)]
+pub struct PostgresConfig {
+    pub database_url: String,
+    /// read-only replica used for exports and web handlers, the primary is used when not set
+    pub replica_url: Option<String>,
+    pub replica_max_lag_secs: u64,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: Option<u64>,
+    pub statement_timeout_ms: Option<u64>,
+    pub application_name: String,
+}
+
+impl PostgresConfig {
+    pub fn new(database_url: String) -> Self {
+        PostgresConfig {
+            database_url,
+            replica_url: None,
+            replica_max_lag_secs: default_replica_max_lag_secs(),
+            max_connections: default_max_connections(),
+            min_connections: 0,
+            acquire_timeout_secs: default_acquire_timeout_secs(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            statement_timeout_ms: None,
+            application_name: default_application_name(),
+        }
+    }
+}
+
+/**
+ * Defaults are used by the [database] section of Settings too, so a pool has the same options with or without the file
+ */
+pub fn default_max_connections() -> u32 {
+    10
+}
+
+pub fn default_acquire_timeout_secs() -> u64 {
+    30
+}
+
+pub fn default_idle_timeout_secs() -> Option<u64> {
+    Some(600)
+}
+
+pub fn default_replica_max_lag_secs() -> u64 {
+    30
+}
+
+fn default_application_name() -> String {
+    "shopsys_sqlx_test".to_string()
+}
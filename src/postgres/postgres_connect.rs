@@ -1,19 +1,136 @@
-use sqlx::postgres::PgPoolOptions;
-use sqlx::{Connection, PgConnection, PgPool, Pool, Postgres};
-use std::env;
+use super::error::PostgresError;
+use super::postgres_config::PostgresConfig;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{Pool, Postgres};
+use std::str::FromStr;
+use std::time::Duration;
 
-pub async fn get_pool() -> Pool<Postgres> {
+/**
+ * Primary database and its optional read-only replica
+ *
+ * Reads that may be slightly stale (exports, web handlers) go to read(),
+ * everything that writes has to use primary()
+ */
+#[derive(Clone)]
+pub struct PostgresPools {
+    primary: Pool<Postgres>,
+    replica: Option<Pool<Postgres>>,
+    replica_max_lag: Duration,
+}
+
+impl PostgresPools {
+    pub fn primary(&self) -> &Pool<Postgres> {
+        &self.primary
+    }
+
+    pub fn read(&self) -> &Pool<Postgres> {
+        self.replica.as_ref().unwrap_or(&self.primary)
+    }
+
+    pub fn has_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+
+    /**
+     * Time since the last transaction replayed on the replica, None when there is no replica
+     */
+    pub async fn get_replica_lag(&self) -> Result<Option<Duration>, PostgresError> {
+        let Some(replica) = &self.replica else {
+            return Ok(None);
+        };
+
+        let lag_seconds = sqlx::query_scalar!(
+            r#"
+            SELECT CASE WHEN pg_is_in_recovery()
+                THEN EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())::float8
+                ELSE 0
+            END AS "lag_seconds: f64"
+            "#
+        )
+        .fetch_one(replica)
+        .await
+        .map_err(PostgresError::Connection)?;
+
+        Ok(Some(Duration::from_secs_f64(
+            lag_seconds.unwrap_or_default().max(0.0),
+        )))
+    }
+
+    /**
+     * Partial exports should run on current data, so a lagging replica is reported before they start
+     */
+    pub async fn warn_if_replica_is_lagging(&self) {
+        match self.get_replica_lag().await {
+            Ok(Some(lag)) if lag > self.replica_max_lag => tracing::warn!(
+                "Read replica is {}s behind the primary, exported data may be stale",
+                lag.as_secs()
+            ),
+            Err(err) => tracing::warn!("Replica lag could not be checked: {}", err),
+            _ => {}
+        }
+    }
+}
+
+pub async fn get_pools(config: &PostgresConfig) -> Result<PostgresPools, PostgresError> {
+    let replica = match &config.replica_url {
+        Some(replica_url) => Some(connect(config, replica_url, "DATABASE_REPLICA_URL").await?),
+        None => None,
+    };
+
+    Ok(PostgresPools {
+        primary: get_pool(config).await?,
+        replica,
+        replica_max_lag: Duration::from_secs(config.replica_max_lag_secs),
+    })
+}
+
+pub async fn get_pool(config: &PostgresConfig) -> Result<Pool<Postgres>, PostgresError> {
     // let conn = PgConnection::connect(&env::var("DATABASE_URL").unwrap())
     //     .await
     //     .unwrap(); // single connection?
 
-    PgPool::connect(&env::var("DATABASE_URL").unwrap())
+    connect(config, &config.database_url, "DATABASE_URL").await
+}
+
+/**
+ * url_name is the env variable of the url, reported when the url is invalid
+ */
+async fn connect(
+    config: &PostgresConfig,
+    database_url: &str,
+    url_name: &str,
+) -> Result<Pool<Postgres>, PostgresError> {
+    let mut connect_options = PgConnectOptions::from_str(database_url)
+        .map_err(|_| PostgresError::InvalidConfigValue(url_name.to_string()))?
+        .application_name(&config.application_name);
+    if let Some(statement_timeout_ms) = config.statement_timeout_ms {
+        connect_options =
+            connect_options.options([("statement_timeout", statement_timeout_ms.to_string())]);
+    }
+
+    PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        .idle_timeout(config.idle_timeout_secs.map(Duration::from_secs))
+        .connect_with(connect_options)
         .await
-        .unwrap() // default with 10 connections
+        .map_err(PostgresError::Connection)
+}
 
-    // PgPoolOptions::new()
-    //     .max_connections(50)
-    //     .connect(&env::var("DATABASE_URL").unwrap())
-    //     .await
-    //     .unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalid_replica_url_is_reported_by_its_name() {
+        let mut config = PostgresConfig::new("postgres://localhost/shopsys".to_string());
+        // port is not a number
+        config.replica_url = Some("postgres://replica:port/shopsys".to_string());
+
+        assert!(matches!(
+            get_pools(&config).await,
+            Err(PostgresError::InvalidConfigValue(name)) if name == "DATABASE_REPLICA_URL"
+        ));
+    }
 }
@@ -0,0 +1,68 @@
+use super::error::PostgresError;
+use sqlx::{Pool, Postgres, Transaction};
+
+/**
+ * Transaction holding a snapshot exported by pg_export_snapshot()
+ *
+ * Other connections can import the snapshot by begin_read_only_transaction(),
+ * so all export workers read the database as it was when the snapshot was taken.
+ * The snapshot is valid only while this transaction is open.
+ */
+pub struct ExportSnapshot {
+    transaction: Transaction<'static, Postgres>,
+    snapshot_id: String,
+}
+
+impl ExportSnapshot {
+    pub async fn begin(pool: &Pool<Postgres>) -> Result<Self, PostgresError> {
+        let mut transaction = begin_read_only_transaction(pool, None)
+            .await
+            .map_err(PostgresError::Connection)?;
+
+        let snapshot_id = sqlx::query_scalar!(r#"SELECT pg_export_snapshot() AS "snapshot_id!""#)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(PostgresError::Connection)?;
+
+        Ok(ExportSnapshot {
+            transaction,
+            snapshot_id,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.snapshot_id
+    }
+
+    pub async fn release(self) -> Result<(), PostgresError> {
+        self.transaction
+            .rollback()
+            .await
+            .map_err(PostgresError::Connection)
+    }
+}
+
+/**
+ * BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY, optionally importing an exported snapshot
+ */
+pub async fn begin_read_only_transaction(
+    pool: &Pool<Postgres>,
+    snapshot_id: Option<&str>,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *transaction)
+        .await?;
+
+    if let Some(snapshot_id) = snapshot_id {
+        // SET TRANSACTION SNAPSHOT does not accept bind parameters
+        sqlx::query(&format!(
+            "SET TRANSACTION SNAPSHOT '{}'",
+            snapshot_id.replace('\'', "''")
+        ))
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    Ok(transaction)
+}
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod product;
 pub mod product_facade;
 pub mod product_repository;
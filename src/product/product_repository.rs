@@ -1,6 +1,5 @@
 use crate::product::product::Product;
-use sqlx::postgres::PgRow;
-use sqlx::{Pool, Postgres, Row};
+use sqlx::{Pool, Postgres};
 
 pub async fn get_products(pool: &Pool<Postgres>, limit: u32) -> Vec<Product> {
     sqlx::query_as::<_, Product>("SELECT * FROM products LIMIT $1")
@@ -10,6 +9,27 @@ pub async fn get_products(pool: &Pool<Postgres>, limit: u32) -> Vec<Product> {
         .unwrap_or_default()
 }
 
+/**
+ * FriendlyUrlRepository::findByDomainIdAndSlug() for the product detail route
+ */
+pub async fn find_product_id_by_slug(
+    pool: &Pool<Postgres>,
+    domain_id: u8,
+    slug: &str,
+) -> Result<Option<i32>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT entity_id
+        FROM friendly_urls
+        WHERE domain_id = $1 AND slug = $2 AND route_name = 'front_product_detail'"#,
+        domain_id as i32,
+        slug
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.entity_id))
+}
+
 // pub async fn find_product_by_id(pool: &Pool<Postgres>, id: i32) -> Vec<Product> {
 //     sqlx::query_as!(Product, "SELECT * FROM products WHERE id = $1", id)
 //         .fetch_all(pool)
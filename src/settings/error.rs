@@ -0,0 +1,31 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum SettingsError {
+    File(String),
+    Parse(String),
+    InvalidEnvValue(String),
+    Invalid(Vec<String>),
+}
+
+impl Error for SettingsError {}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::File(message) => {
+                write!(f, "Settings file could not be read: {}", message)
+            }
+            SettingsError::Parse(message) => {
+                write!(f, "Settings file could not be parsed: {}", message)
+            }
+            SettingsError::InvalidEnvValue(name) => {
+                write!(f, "Invalid value of env variable '{}'", name)
+            }
+            SettingsError::Invalid(problems) => {
+                write!(f, "Invalid settings: {}", problems.join("; "))
+            }
+        }
+    }
+}
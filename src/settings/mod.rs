@@ -0,0 +1,3 @@
+pub mod error;
+#[allow(clippy::module_inception)]
+pub mod settings;
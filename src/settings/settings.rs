@@ -0,0 +1,636 @@
+use super::error::SettingsError;
+use crate::domain::domain_config::DomainConfig;
+use crate::elastic::index_definition_loader::DEFAULT_DEFINITIONS_DIRECTORY;
+use crate::postgres::postgres_config::{
+    default_acquire_timeout_secs, default_idle_timeout_secs, default_max_connections,
+    default_replica_max_lag_secs, PostgresConfig, EXPORTER_PROFILE, WEB_SERVER_PROFILE,
+};
+use elasticsearch::http::Url;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::env;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const DEFAULT_SETTINGS_PATH: &str = "./config/settings.toml";
+
+/**
+ * Settings of the web server and the console, loaded from config/settings.toml
+ *
+ * Values from the file can be overridden by env variables (see apply_env_overrides),
+ * so secrets and per-environment values do not have to be in the file.
+ */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default)]
+    pub server: ServerSettings,
+    #[serde(default)]
+    pub elasticsearch: ElasticsearchSettings,
+    #[serde(default)]
+    pub export: ExportSettings,
+    #[serde(default)]
+    pub database: DatabaseSettings,
+    #[serde(default)]
+    pub domains: Vec<DomainConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerSettings {
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    /// listener of the admin routes and /metrics, should not be reachable from the internet
+    #[serde(default = "default_admin_bind_address")]
+    pub admin_bind_address: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElasticsearchSettings {
+    #[serde(default = "default_elasticsearch_url")]
+    pub url: String,
+    /// more nodes of the cluster, requests are spread over them, "url" is not used when set
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// PEM file of a custom CA, the system CAs are used when not set
+    #[serde(default)]
+    pub ca_certificate_path: Option<String>,
+    #[serde(default = "default_elasticsearch_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub index_prefix: String,
+    #[serde(default = "default_definitions_directory")]
+    pub definitions_directory: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportSettings {
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u32,
+    /// number of batches exported at once
+    #[serde(default = "default_concurrency")]
+    pub concurrency: u32,
+    /// documents are checked against the mapping of the definition before they are sent
+    #[serde(default)]
+    pub strict_mapping: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseSettings {
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub replica_url: Option<String>,
+    #[serde(default = "default_replica_max_lag_secs")]
+    pub replica_max_lag_secs: u64,
+    #[serde(default)]
+    pub statement_timeout_ms: Option<u64>,
+    #[serde(default = "default_web_pool")]
+    pub web: PoolSettings,
+    #[serde(default = "default_exporter_pool")]
+    pub exporter: PoolSettings,
+}
+
+/**
+ * Pool options of one profile, the web server and the exporter need different pool sizes
+ */
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolSettings {
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    #[serde(default)]
+    pub min_connections: u32,
+    #[serde(default = "default_acquire_timeout_secs")]
+    pub acquire_timeout_secs: u64,
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub application_name: Option<String>,
+}
+
+impl Settings {
+    /**
+     * File from SETTINGS_FILE, or config/settings.toml when it exists, then env overrides and validation
+     * without the database settings, see validate_database
+     */
+    pub fn load() -> Result<Self, SettingsError> {
+        let mut settings = match env::var("SETTINGS_FILE") {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if Path::new(DEFAULT_SETTINGS_PATH).exists() => {
+                Self::from_file(DEFAULT_SETTINGS_PATH)?
+            }
+            Err(_) => Self::from_toml("")?,
+        };
+        settings.apply_env_overrides()?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, SettingsError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| SettingsError::File(format!("{}: {}", path, err)))?;
+
+        Self::from_toml(&content).map_err(|err| match err {
+            SettingsError::Parse(message) => SettingsError::Parse(format!("{}: {}", path, message)),
+            err => err,
+        })
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, SettingsError> {
+        toml::from_str(content).map_err(|err| SettingsError::Parse(err.to_string()))
+    }
+
+    /**
+     * Env variables used before the settings file existed keep working as overrides:
+     * DATABASE_URL, DATABASE_REPLICA_URL, DATABASE_<PROFILE>_<NAME> / DATABASE_<NAME> for pool options,
+     * ELASTIC_SEARCH_INDEX_PREFIX, ELASTICSEARCH_URL, ELASTICSEARCH_NODES (comma separated),
+     * ELASTICSEARCH_USERNAME, ELASTICSEARCH_PASSWORD, ELASTICSEARCH_API_KEY, ELASTICSEARCH_CA_CERTIFICATE,
+     * ELASTICSEARCH_TIMEOUT, ELASTICSEARCH_DEFINITIONS_DIRECTORY, EXPORT_BATCH_SIZE, EXPORT_CONCURRENCY,
+     * EXPORT_STRICT_MAPPING and SERVER_BIND_ADDRESS
+     */
+    pub fn apply_env_overrides(&mut self) -> Result<(), SettingsError> {
+        override_value(&mut self.server.bind_address, &["SERVER_BIND_ADDRESS"])?;
+        override_value(
+            &mut self.server.admin_bind_address,
+            &["SERVER_ADMIN_BIND_ADDRESS"],
+        )?;
+
+        let elasticsearch = &mut self.elasticsearch;
+        override_value(&mut elasticsearch.url, &["ELASTICSEARCH_URL"])?;
+        if let Some(nodes) = env_value::<String>(&["ELASTICSEARCH_NODES"])? {
+            elasticsearch.nodes = nodes
+                .split(',')
+                .map(|node| node.trim().to_string())
+                .filter(|node| !node.is_empty())
+                .collect();
+        }
+        override_option(&mut elasticsearch.username, &["ELASTICSEARCH_USERNAME"])?;
+        override_option(&mut elasticsearch.password, &["ELASTICSEARCH_PASSWORD"])?;
+        override_option(&mut elasticsearch.api_key, &["ELASTICSEARCH_API_KEY"])?;
+        override_option(
+            &mut elasticsearch.ca_certificate_path,
+            &["ELASTICSEARCH_CA_CERTIFICATE"],
+        )?;
+        override_value(&mut elasticsearch.timeout_secs, &["ELASTICSEARCH_TIMEOUT"])?;
+        override_value(
+            &mut elasticsearch.index_prefix,
+            &["ELASTIC_SEARCH_INDEX_PREFIX"],
+        )?;
+        override_value(
+            &mut elasticsearch.definitions_directory,
+            &["ELASTICSEARCH_DEFINITIONS_DIRECTORY"],
+        )?;
+
+        override_value(&mut self.export.batch_size, &["EXPORT_BATCH_SIZE"])?;
+        override_value(&mut self.export.concurrency, &["EXPORT_CONCURRENCY"])?;
+        override_value(&mut self.export.strict_mapping, &["EXPORT_STRICT_MAPPING"])?;
+
+        let database = &mut self.database;
+        override_value(&mut database.url, &["DATABASE_URL"])?;
+        override_option(&mut database.replica_url, &["DATABASE_REPLICA_URL"])?;
+        override_value(
+            &mut database.replica_max_lag_secs,
+            &["DATABASE_REPLICA_MAX_LAG"],
+        )?;
+        override_option(
+            &mut database.statement_timeout_ms,
+            &["DATABASE_STATEMENT_TIMEOUT"],
+        )?;
+        database.web.apply_env_overrides(WEB_SERVER_PROFILE)?;
+        database.exporter.apply_env_overrides(EXPORTER_PROFILE)?;
+
+        Ok(())
+    }
+
+    /**
+     * All problems are reported at once, so the settings do not have to be fixed one by one
+     */
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let mut problems = vec![];
+
+        if SocketAddr::from_str(&self.server.bind_address).is_err() {
+            problems.push(format!(
+                "server.bind_address '{}' is not a valid socket address",
+                self.server.bind_address
+            ));
+        }
+        if SocketAddr::from_str(&self.server.admin_bind_address).is_err() {
+            problems.push(format!(
+                "server.admin_bind_address '{}' is not a valid socket address",
+                self.server.admin_bind_address
+            ));
+        }
+        if self.server.admin_bind_address == self.server.bind_address {
+            problems
+                .push("server.admin_bind_address must differ from server.bind_address".to_string());
+        }
+
+        let elasticsearch = &self.elasticsearch;
+        for url in elasticsearch.get_node_urls() {
+            if Url::parse(url).is_err() {
+                problems.push(format!("elasticsearch node '{}' is not a valid url", url));
+            }
+        }
+        if let Some(ca_certificate_path) = &elasticsearch.ca_certificate_path {
+            if !Path::new(ca_certificate_path).is_file() {
+                problems.push(format!(
+                    "elasticsearch.ca_certificate_path '{}' is not a file",
+                    ca_certificate_path
+                ));
+            }
+        }
+        if elasticsearch.timeout_secs == 0 {
+            problems.push("elasticsearch.timeout_secs must be greater than 0".to_string());
+        }
+        if elasticsearch.username.is_some() != elasticsearch.password.is_some() {
+            problems.push(
+                "elasticsearch.username and elasticsearch.password must be set together"
+                    .to_string(),
+            );
+        }
+        if elasticsearch.username.is_some() && elasticsearch.api_key.is_some() {
+            problems.push(
+                "elasticsearch.api_key can not be combined with username and password".to_string(),
+            );
+        }
+        if !Path::new(&elasticsearch.definitions_directory).is_dir() {
+            problems.push(format!(
+                "elasticsearch.definitions_directory '{}' is not a directory",
+                elasticsearch.definitions_directory
+            ));
+        }
+
+        if self.export.batch_size == 0 {
+            problems.push("export.batch_size must be greater than 0".to_string());
+        }
+        if self.export.concurrency == 0 {
+            problems.push("export.concurrency must be greater than 0".to_string());
+        }
+
+        if self.domains.is_empty() {
+            problems.push("no domains are configured".to_string());
+        }
+        let mut domain_ids = BTreeSet::new();
+        for domain in &self.domains {
+            if !domain_ids.insert(domain.id) {
+                problems.push(format!("domain {} is configured more than once", domain.id));
+            }
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(SettingsError::Invalid(problems)),
+        }
+    }
+
+    /**
+     * Database settings are checked only by the commands that open a pool,
+     * so e.g. elasticsearch:create or elasticsearch:list do not need DATABASE_URL
+     */
+    pub fn validate_database(&self) -> Result<(), SettingsError> {
+        let mut problems = vec![];
+
+        if self.database.url.is_empty() {
+            problems.push("database.url is not set (or DATABASE_URL)".to_string());
+        }
+        for (profile, pool) in [
+            (WEB_SERVER_PROFILE, &self.database.web),
+            (EXPORTER_PROFILE, &self.database.exporter),
+        ] {
+            if pool.max_connections == 0 {
+                problems.push(format!(
+                    "database.{}.max_connections must be greater than 0",
+                    profile
+                ));
+            }
+            if pool.min_connections > pool.max_connections {
+                problems.push(format!(
+                    "database.{}.min_connections is greater than max_connections",
+                    profile
+                ));
+            }
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(SettingsError::Invalid(problems)),
+        }
+    }
+
+    pub fn get_postgres_config(&self, profile: &str) -> PostgresConfig {
+        let pool = match profile {
+            EXPORTER_PROFILE => &self.database.exporter,
+            _ => &self.database.web,
+        };
+
+        let mut config = PostgresConfig::new(self.database.url.clone());
+        config.replica_url = self.database.replica_url.clone();
+        config.replica_max_lag_secs = self.database.replica_max_lag_secs;
+        config.statement_timeout_ms = self.database.statement_timeout_ms;
+        config.max_connections = pool.max_connections;
+        config.min_connections = pool.min_connections;
+        config.acquire_timeout_secs = pool.acquire_timeout_secs;
+        config.idle_timeout_secs = pool.idle_timeout_secs;
+        config.application_name = pool
+            .application_name
+            .clone()
+            .unwrap_or_else(|| format!("{}_{}", config.application_name, profile));
+
+        config
+    }
+
+    pub fn get_domain(&self, domain_id: u8) -> Option<&DomainConfig> {
+        self.domains.iter().find(|domain| domain.id == domain_id)
+    }
+
+    pub fn get_domain_ids(&self) -> Vec<u8> {
+        self.domains.iter().map(|domain| domain.id).collect()
+    }
+}
+
+impl ElasticsearchSettings {
+    pub fn get_node_urls(&self) -> Vec<&str> {
+        match self.nodes.is_empty() {
+            true => vec![self.url.as_str()],
+            false => self.nodes.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl PoolSettings {
+    /**
+     * DATABASE_<PROFILE>_<NAME> takes precedence over DATABASE_<NAME>,
+     * e.g. DATABASE_EXPORTER_MAX_CONNECTIONS=50 and DATABASE_WEB_MAX_CONNECTIONS=10
+     */
+    fn apply_env_overrides(&mut self, profile: &str) -> Result<(), SettingsError> {
+        let names = |name: &str| {
+            [
+                format!("DATABASE_{}_{}", profile.to_uppercase(), name),
+                format!("DATABASE_{}", name),
+            ]
+        };
+
+        override_value(&mut self.max_connections, &names("MAX_CONNECTIONS"))?;
+        override_value(&mut self.min_connections, &names("MIN_CONNECTIONS"))?;
+        override_value(&mut self.acquire_timeout_secs, &names("ACQUIRE_TIMEOUT"))?;
+        override_option(&mut self.idle_timeout_secs, &names("IDLE_TIMEOUT"))?;
+        override_option(&mut self.application_name, &names("APPLICATION_NAME"))?;
+
+        Ok(())
+    }
+}
+
+/**
+ * First of the env variables that is set wins
+ */
+fn env_value<T: FromStr>(names: &[impl AsRef<str>]) -> Result<Option<T>, SettingsError> {
+    for name in names {
+        if let Ok(value) = env::var(name.as_ref()) {
+            return value
+                .parse()
+                .map(Some)
+                .map_err(|_| SettingsError::InvalidEnvValue(name.as_ref().to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+fn override_value<T: FromStr>(
+    value: &mut T,
+    names: &[impl AsRef<str>],
+) -> Result<(), SettingsError> {
+    if let Some(env_value) = env_value(names)? {
+        *value = env_value;
+    }
+
+    Ok(())
+}
+
+fn override_option<T: FromStr>(
+    value: &mut Option<T>,
+    names: &[impl AsRef<str>],
+) -> Result<(), SettingsError> {
+    if let Some(env_value) = env_value(names)? {
+        *value = Some(env_value);
+    }
+
+    Ok(())
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            bind_address: default_bind_address(),
+            admin_bind_address: default_admin_bind_address(),
+        }
+    }
+}
+
+impl Default for ElasticsearchSettings {
+    fn default() -> Self {
+        ElasticsearchSettings {
+            url: default_elasticsearch_url(),
+            nodes: vec![],
+            username: None,
+            password: None,
+            api_key: None,
+            ca_certificate_path: None,
+            timeout_secs: default_elasticsearch_timeout_secs(),
+            index_prefix: String::new(),
+            definitions_directory: default_definitions_directory(),
+        }
+    }
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            batch_size: default_batch_size(),
+            concurrency: default_concurrency(),
+            strict_mapping: false,
+        }
+    }
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            url: String::new(),
+            replica_url: None,
+            replica_max_lag_secs: default_replica_max_lag_secs(),
+            statement_timeout_ms: None,
+            web: default_web_pool(),
+            exporter: default_exporter_pool(),
+        }
+    }
+}
+
+fn default_bind_address() -> String {
+    "127.0.0.1:3000".to_string()
+}
+
+fn default_admin_bind_address() -> String {
+    "127.0.0.1:3001".to_string()
+}
+
+fn default_elasticsearch_url() -> String {
+    "http://localhost:9200".to_string()
+}
+
+fn default_elasticsearch_timeout_secs() -> u64 {
+    30
+}
+
+fn default_definitions_directory() -> String {
+    DEFAULT_DEFINITIONS_DIRECTORY.to_string()
+}
+
+fn default_batch_size() -> u32 {
+    100
+}
+
+fn default_concurrency() -> u32 {
+    1
+}
+
+fn default_web_pool() -> PoolSettings {
+    PoolSettings {
+        max_connections: default_max_connections(),
+        min_connections: 0,
+        acquire_timeout_secs: default_acquire_timeout_secs(),
+        idle_timeout_secs: default_idle_timeout_secs(),
+        application_name: None,
+    }
+}
+
+fn default_exporter_pool() -> PoolSettings {
+    PoolSettings {
+        max_connections: 50,
+        ..default_web_pool()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = r#"
+        [export]
+        batch_size = 200
+
+        [database.exporter]
+        max_connections = 40
+
+        [[domains]]
+        id = 1
+        name = "shopsys"
+        locale = "cs"
+        pricing_group_id = 1
+    "#;
+
+    /**
+     * Env variables are shared by all tests of the process, so they are set only in this test
+     */
+    #[test]
+    fn env_overrides_values_of_file() {
+        let mut settings = Settings::from_toml(SETTINGS).unwrap();
+        env::set_var("EXPORT_BATCH_SIZE", "500");
+        env::set_var("DATABASE_MAX_CONNECTIONS", "20");
+        env::set_var("DATABASE_EXPORTER_MAX_CONNECTIONS", "80");
+        let result = settings.apply_env_overrides();
+        env::set_var("EXPORT_CONCURRENCY", "many");
+        let invalid_result = Settings::from_toml(SETTINGS).unwrap().apply_env_overrides();
+        for name in [
+            "EXPORT_BATCH_SIZE",
+            "DATABASE_MAX_CONNECTIONS",
+            "DATABASE_EXPORTER_MAX_CONNECTIONS",
+            "EXPORT_CONCURRENCY",
+        ] {
+            env::remove_var(name);
+        }
+
+        result.unwrap();
+        assert_eq!(settings.export.batch_size, 500);
+        // profile variable wins over the shared one
+        assert_eq!(settings.database.exporter.max_connections, 80);
+        assert_eq!(settings.database.web.max_connections, 20);
+        assert!(matches!(
+            invalid_result,
+            Err(SettingsError::InvalidEnvValue(name)) if name == "EXPORT_CONCURRENCY"
+        ));
+    }
+
+    #[test]
+    fn defaults_of_missing_values() {
+        let settings = Settings::from_toml(SETTINGS).unwrap();
+        let default_config = PostgresConfig::new(String::new());
+
+        assert_eq!(settings.export.concurrency, 1);
+        assert_eq!(
+            settings.database.web.max_connections,
+            default_config.max_connections
+        );
+        assert_eq!(
+            settings.database.exporter.acquire_timeout_secs,
+            default_config.acquire_timeout_secs
+        );
+        assert_eq!(
+            settings.database.replica_max_lag_secs,
+            default_config.replica_max_lag_secs
+        );
+    }
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let mut settings = Settings::from_toml(SETTINGS).unwrap();
+        assert!(settings.validate().is_ok());
+
+        settings.server.bind_address = "localhost".to_string();
+        settings.export.batch_size = 0;
+        settings.elasticsearch.username = Some("elastic".to_string());
+        settings.domains.push(settings.domains[0].clone());
+
+        let Err(SettingsError::Invalid(problems)) = settings.validate() else {
+            panic!("settings should be invalid");
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "server.bind_address 'localhost' is not a valid socket address",
+                "elasticsearch.username and elasticsearch.password must be set together",
+                "export.batch_size must be greater than 0",
+                "domain 1 is configured more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_database_checks_pools() {
+        let mut settings = Settings::from_toml(SETTINGS).unwrap();
+        settings.database.web.min_connections = 20;
+
+        let Err(SettingsError::Invalid(problems)) = settings.validate_database() else {
+            panic!("database settings should be invalid");
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "database.url is not set (or DATABASE_URL)",
+                "database.web.min_connections is greater than max_connections",
+            ]
+        );
+    }
+}
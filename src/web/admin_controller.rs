@@ -0,0 +1,38 @@
+use crate::elastic::index_facade::IndexFacade;
+use crate::web::app_state::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+/**
+ * GET /admin/indexes
+ */
+pub async fn index_status(State(state): State<AppState>) -> Response {
+    let index_definitions = match state
+        .index_definition_loader
+        .get_definitions(&state.settings.get_domain_ids())
+    {
+        Ok(index_definitions) => index_definitions,
+        Err(err) => return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(
+                json!({ "error": format!("Index definitions could not be loaded. Error: {err}") }),
+            ),
+        )
+            .into_response(),
+    };
+
+    match IndexFacade::new(&state.index_repository)
+        .get_status_report(&index_definitions)
+        .await
+    {
+        Ok(report) => Json(report).into_response(),
+        Err(err) => (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({ "error": format!("Index status could not be loaded. Error: {err}") })),
+        )
+            .into_response(),
+    }
+}
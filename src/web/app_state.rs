@@ -0,0 +1,29 @@
+use crate::domain::domain_config::DomainConfig;
+use crate::elastic::index_definition::IndexDefinition;
+use crate::elastic::index_definition_loader::IndexDefinitionLoader;
+use crate::elastic::index_repository::IndexRepository;
+use crate::postgres::postgres_connect::PostgresPools;
+use crate::settings::settings::Settings;
+use std::sync::Arc;
+
+/**
+ * Shared by all handlers of the web server, so the pool and the ES client are created only once
+ */
+#[derive(Clone)]
+pub struct AppState {
+    pub pools: PostgresPools,
+    pub index_repository: IndexRepository,
+    pub index_definition_loader: Arc<IndexDefinitionLoader>,
+    pub settings: Arc<Settings>,
+}
+
+impl AppState {
+    pub fn get_domain(&self, domain_id: u8) -> Option<&DomainConfig> {
+        self.settings.get_domain(domain_id)
+    }
+
+    pub fn get_index_definition(&self, index_name: &str, domain_id: u8) -> IndexDefinition {
+        self.index_definition_loader
+            .get_definition(index_name.to_string(), domain_id)
+    }
+}
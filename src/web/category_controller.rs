@@ -0,0 +1,165 @@
+use crate::category::category_repository::find_category_name;
+use crate::elastic::category_search::{CategoryMenuItem, CategorySearch};
+use crate::elastic::product_query::{
+    ProductQuery, ORDER_BY_NAME_ASC, ORDER_BY_NAME_DESC, ORDER_BY_PRICE_ASC, ORDER_BY_PRICE_DESC,
+    ORDER_BY_PRIORITY,
+};
+use crate::elastic::product_search::{ProductListItem, ProductSearch, PRODUCTS_PER_PAGE};
+use crate::web::app_state::AppState;
+use crate::web::pagination::Pagination;
+use crate::web::{api_error_response, HtmlTemplate, DOMAIN_NOT_FOUND};
+use askama::Template;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+
+const ORDERING_MODES: [(&str, &str); 5] = [
+    (ORDER_BY_PRIORITY, "Doporučujeme"),
+    (ORDER_BY_PRICE_ASC, "Nejlevnější"),
+    (ORDER_BY_PRICE_DESC, "Nejdražší"),
+    (ORDER_BY_NAME_ASC, "Abecedně A-Z"),
+    (ORDER_BY_NAME_DESC, "Abecedně Z-A"),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryDetailParams {
+    page: Option<u32>,
+    sort: Option<String>,
+    domain: Option<u8>,
+}
+
+struct OrderingModeLink {
+    id: &'static str,
+    label: &'static str,
+    is_selected: bool,
+}
+
+#[derive(Template)]
+#[template(path = "categoryDetail.askama.html")]
+struct CategoryDetailTemplate {
+    category_id: i32,
+    category_name: String,
+    domain_id: u8,
+    products: Vec<ProductListItem>,
+    pagination: Pagination,
+    ordering_mode_id: String,
+    ordering_modes: Vec<OrderingModeLink>,
+    menu: Vec<CategoryMenuItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CategoryMenuParams {
+    domain: Option<u8>,
+}
+
+/**
+ * GET /category/:id?page=&sort=&domain=
+ */
+pub async fn category_detail(
+    State(state): State<AppState>,
+    Path(category_id): Path<i32>,
+    Query(params): Query<CategoryDetailParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+    let category_name = match find_category_name(
+        state.pools.read(),
+        category_id,
+        domain.id,
+        &domain.locale,
+    )
+    .await
+    {
+        Ok(Some(category_name)) => category_name,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Category not found").into_response(),
+        Err(err) => {
+            tracing::error!("Category {category_id} could not be loaded: {err:?}");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Category could not be loaded",
+            )
+                .into_response();
+        }
+    };
+
+    let index_definition = state.get_index_definition("product", domain.id);
+
+    let page = params.page.unwrap_or(1).max(1);
+    let ordering_mode_id = ORDERING_MODES
+        .iter()
+        .map(|(id, _)| *id)
+        .find(|id| Some(*id) == params.sort.as_deref())
+        .unwrap_or(ORDER_BY_PRIORITY);
+    let product_query = ProductQuery::new()
+        .filter_by_categories(vec![category_id])
+        .apply_ordering(ordering_mode_id, domain.pricing_group_id)
+        .paginate(page, PRODUCTS_PER_PAGE);
+
+    let product_search = ProductSearch::new(&state.index_repository);
+    let category_search = CategorySearch::new(&state.index_repository);
+    let category_index_definition = state.get_index_definition("category", domain.id);
+    let (result, menu) = tokio::join!(
+        product_search.search_by_query(&index_definition, &product_query),
+        category_search.get_menu(&category_index_definition),
+    );
+    // the page is usable without the menu, e.g. before the category index is exported
+    let menu = menu.unwrap_or_else(|err| {
+        tracing::warn!("Category menu could not be loaded from index: {err:?}");
+        vec![]
+    });
+
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            tracing::error!("Failed to load products of category {category_id}: {err:?}");
+            return (StatusCode::BAD_GATEWAY, "Failed to load products").into_response();
+        }
+    };
+
+    HtmlTemplate(CategoryDetailTemplate {
+        category_id,
+        category_name,
+        domain_id: domain.id,
+        products: result
+            .hits
+            .iter()
+            .map(|source| ProductListItem::from_source(source, domain.pricing_group_id))
+            .collect(),
+        pagination: Pagination::new(page, result.total, PRODUCTS_PER_PAGE),
+        ordering_mode_id: ordering_mode_id.to_string(),
+        ordering_modes: ORDERING_MODES
+            .iter()
+            .map(|(id, label)| OrderingModeLink {
+                id,
+                label,
+                is_selected: *id == ordering_mode_id,
+            })
+            .collect(),
+        menu,
+    })
+    .into_response()
+}
+
+/**
+ * GET /api/categories/menu?domain=
+ */
+pub async fn category_menu(
+    State(state): State<AppState>,
+    Query(params): Query<CategoryMenuParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+    let index_definition = state.get_index_definition("category", domain.id);
+
+    match CategorySearch::new(&state.index_repository)
+        .get_menu(&index_definition)
+        .await
+    {
+        Ok(menu) => Json(menu).into_response(),
+        Err(err) => api_error_response(StatusCode::BAD_GATEWAY, "Category menu failed", err),
+    }
+}
@@ -0,0 +1,13 @@
+use crate::metrics::metrics;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+
+/**
+ * GET /metrics - Prometheus scrape endpoint
+ */
+pub async fn metrics() -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
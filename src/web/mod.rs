@@ -0,0 +1,46 @@
+pub mod admin_controller;
+pub mod app_state;
+pub mod category_controller;
+pub mod metrics_controller;
+pub mod pagination;
+pub mod product_controller;
+pub mod search_controller;
+
+use askama::Template;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::fmt::Debug;
+
+pub const DOMAIN_NOT_FOUND: (StatusCode, &str) = (StatusCode::NOT_FOUND, "Domain not found");
+
+/**
+ * The error is only logged, its details (queries, index names, hosts) are not for the clients
+ */
+pub fn api_error_response(status: StatusCode, message: &str, err: impl Debug) -> Response {
+    tracing::error!("{message}: {err:?}");
+
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+pub struct HtmlTemplate<T>(pub T);
+
+impl<T> IntoResponse for HtmlTemplate<T>
+where
+    T: Template,
+{
+    fn into_response(self) -> Response {
+        match self.0.render() {
+            Ok(html) => Html(html).into_response(),
+            Err(err) => {
+                tracing::error!("Failed to render template: {err:?}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to render template",
+                )
+                    .into_response()
+            }
+        }
+    }
+}
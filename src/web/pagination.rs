@@ -0,0 +1,76 @@
+use crate::elastic::product_query::MAX_RESULT_WINDOW;
+
+const PAGES_AROUND_CURRENT: u32 = 2;
+
+pub struct PageLink {
+    pub page: u32,
+    pub is_current: bool,
+}
+
+/**
+ * Paging of a product list, page is counted from 1,
+ * pages behind MAX_RESULT_WINDOW of the index are not offered as they would be empty
+ */
+pub struct Pagination {
+    pub page: u32,
+    pub page_count: u32,
+    pub total_count: u64,
+}
+
+impl Pagination {
+    pub fn new(page: u32, total_count: u64, page_size: u32) -> Self {
+        Pagination {
+            page,
+            page_count: total_count
+                .div_ceil(page_size as u64)
+                .min((MAX_RESULT_WINDOW / page_size) as u64) as u32,
+            total_count,
+        }
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.page > 1
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.page < self.page_count
+    }
+
+    pub fn previous(&self) -> u32 {
+        self.page - 1
+    }
+
+    pub fn next(&self) -> u32 {
+        self.page + 1
+    }
+
+    /**
+     * Links to the pages around the current one
+     */
+    pub fn get_page_links(&self) -> Vec<PageLink> {
+        let first = self.page.saturating_sub(PAGES_AROUND_CURRENT).max(1);
+        let last = self
+            .page
+            .saturating_add(PAGES_AROUND_CURRENT)
+            .min(self.page_count);
+
+        (first..=last)
+            .map(|page| PageLink {
+                page,
+                is_current: page == self.page,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_count_stays_in_max_result_window() {
+        assert_eq!(Pagination::new(1, 45, 20).page_count, 3);
+        assert_eq!(Pagination::new(1, 1_000_000, 20).page_count, 1500);
+        assert_eq!(Pagination::new(1, u64::MAX, 20).page_count, 1500);
+    }
+}
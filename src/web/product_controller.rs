@@ -0,0 +1,173 @@
+use crate::elastic::error::ElasticError;
+use crate::elastic::export_source::ExportSource;
+use crate::elastic::index::Index;
+use crate::elastic::index_repository::IndexRepository;
+use crate::elastic::product_index::ProductIndex;
+use crate::elastic::product_search::get_price_with_vat;
+use crate::product::product_repository::find_product_id_by_slug;
+use crate::web::app_state::AppState;
+use crate::web::{HtmlTemplate, DOMAIN_NOT_FOUND};
+use askama::Template;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Deserialize)]
+pub struct ProductDetailParams {
+    domain: Option<u8>,
+}
+
+struct ProductParameter {
+    name: String,
+    value: String,
+    unit: Option<String>,
+}
+
+/**
+ * Product detail data, read from the product document no matter if it comes from the index or from Postgres
+ */
+struct ProductDetail {
+    name: String,
+    fullname: String,
+    description: String,
+    price: Option<f64>,
+    availability: Option<String>,
+    parameters: Vec<ProductParameter>,
+    variant_ids: Vec<i64>,
+}
+
+#[derive(Template)]
+#[template(path = "productDetail.askama.html")]
+struct ProductDetailTemplate {
+    product: ProductDetail,
+    domain_id: u8,
+}
+
+/**
+ * GET /product/:slug_or_id?domain=
+ */
+pub async fn product_detail(
+    State(state): State<AppState>,
+    Path(slug_or_id): Path<String>,
+    Query(params): Query<ProductDetailParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+    let index_definition = state.get_index_definition("product", domain.id);
+
+    let source = match find_product_document(
+        &state.index_repository,
+        &index_definition.get_index_alias(),
+        &slug_or_id,
+    )
+    .await
+    {
+        Some(source) => Ok(Some(source)),
+        None => {
+            let export_source =
+                ExportSource::new(state.pools.read().clone(), state.settings.domains.clone());
+            find_product_in_postgres(export_source, domain.id, &slug_or_id).await
+        }
+    };
+
+    match source {
+        Ok(Some(source)) => HtmlTemplate(ProductDetailTemplate {
+            product: ProductDetail::from_source(&source, domain.pricing_group_id),
+            domain_id: domain.id,
+        })
+        .into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Product not found").into_response(),
+        Err(err) => {
+            tracing::error!("Failed to load product '{slug_or_id}': {err:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load product").into_response()
+        }
+    }
+}
+
+async fn find_product_document(
+    index_repository: &IndexRepository,
+    index_alias: &str,
+    slug_or_id: &str,
+) -> Option<Value> {
+    let document = match slug_or_id.parse::<i32>() {
+        Ok(id) => {
+            index_repository
+                .get_document(index_alias, &id.to_string())
+                .await
+        }
+        Err(_) => index_repository
+            .search(
+                index_alias,
+                json!({
+                    "query": { "term": { "slug": slug_or_id } },
+                    "size": 1,
+                }),
+            )
+            .await
+            .map(|response_body| response_body["hits"]["hits"][0].get("_source").cloned()),
+    };
+
+    document.unwrap_or_else(|err| {
+        tracing::warn!("Product '{slug_or_id}' could not be loaded from index: {err:?}");
+        None
+    })
+}
+
+/**
+ * Fallback for products that are not exported yet, the document is assembled by ProductIndex the same way the export does
+ */
+async fn find_product_in_postgres(
+    export_source: ExportSource,
+    domain_id: u8,
+    slug_or_id: &str,
+) -> Result<Option<Value>, ElasticError> {
+    let product_id = match slug_or_id.parse::<i32>() {
+        Ok(id) => id,
+        Err(_) => match find_product_id_by_slug(export_source.get_pool(), domain_id, slug_or_id)
+            .await
+            .map_err(ElasticError::Postgres)?
+        {
+            Some(id) => id,
+            None => return Ok(None),
+        },
+    };
+
+    Ok(ProductIndex::new(export_source)
+        .get_export_data_for_ids(domain_id, vec![product_id])
+        .await?
+        .remove(&product_id)
+        .and_then(|export_data| serde_json::to_value(export_data).ok()))
+}
+
+impl ProductDetail {
+    fn from_source(source: &Value, pricing_group_id: i32) -> Self {
+        let as_string = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+        ProductDetail {
+            name: as_string(&source["name"]),
+            fullname: as_string(&source["full_name"]),
+            description: as_string(&source["description"]),
+            price: get_price_with_vat(source, pricing_group_id),
+            availability: source["availability"].as_str().map(str::to_string),
+            parameters: source["parameters"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|parameter| ProductParameter {
+                    name: as_string(&parameter["parameter_name"]),
+                    value: as_string(&parameter["parameter_value_text"]),
+                    unit: parameter["parameter_unit"].as_str().map(str::to_string),
+                })
+                .collect(),
+            variant_ids: source["variants"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_i64)
+                .collect(),
+        }
+    }
+}
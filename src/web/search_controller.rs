@@ -0,0 +1,207 @@
+use crate::elastic::article_search::{
+    ArticleSearch, ARTICLE_SEARCH_LIMIT, ARTICLE_SEARCH_MAX_LIMIT,
+};
+use crate::elastic::category_search::{
+    CategorySearch, CATEGORY_SEARCH_LIMIT, CATEGORY_SEARCH_MAX_LIMIT,
+};
+use crate::elastic::product_autocomplete::{
+    ProductAutocomplete, AUTOCOMPLETE_PRODUCTS_LIMIT, AUTOCOMPLETE_PRODUCTS_MAX_LIMIT,
+};
+use crate::elastic::product_filter::ProductFilterData;
+use crate::elastic::product_search::ProductSearch;
+use crate::web::app_state::AppState;
+use crate::web::{api_error_response, DOMAIN_NOT_FOUND};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Deserialize)]
+pub struct ProductSearchParams {
+    #[serde(default)]
+    q: String,
+    domain: Option<u8>,
+    page: Option<u32>,
+    /// comma separated ids, e.g. brands=1,2
+    #[serde(default)]
+    brands: String,
+    #[serde(default)]
+    flags: String,
+    #[serde(default)]
+    categories: String,
+    price_from: Option<f64>,
+    price_to: Option<f64>,
+    #[serde(default)]
+    in_stock: bool,
+    /// comma separated parameter_id:parameter_value_id pairs, e.g. parameters=5:10,5:11,6:20
+    #[serde(default)]
+    parameters: String,
+}
+
+impl ProductSearchParams {
+    fn get_filter_data(&self, pricing_group_id: i32) -> ProductFilterData {
+        let mut parameters: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for pair in self.parameters.split(',') {
+            if let Some((parameter_id, value_id)) = pair.split_once(':') {
+                if let (Ok(parameter_id), Ok(value_id)) =
+                    (parameter_id.trim().parse(), value_id.trim().parse())
+                {
+                    parameters.entry(parameter_id).or_default().push(value_id);
+                }
+            }
+        }
+
+        ProductFilterData {
+            brands: parse_ids(&self.brands),
+            flags: parse_ids(&self.flags),
+            categories: parse_ids(&self.categories),
+            minimal_price: self.price_from,
+            maximal_price: self.price_to,
+            in_stock: self.in_stock,
+            parameters,
+            pricing_group_id,
+        }
+    }
+}
+
+/**
+ * GET /api/products/search?q=&domain=&page=&brands=&flags=&categories=&price_from=&price_to=&in_stock=&parameters=
+ */
+pub async fn search_products(
+    State(state): State<AppState>,
+    Query(params): Query<ProductSearchParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+    let index_definition = state.get_index_definition("product", domain.id);
+
+    match ProductSearch::new(&state.index_repository)
+        .search(
+            &index_definition,
+            &params.q,
+            &params.get_filter_data(domain.pricing_group_id),
+            params.page.unwrap_or(1),
+        )
+        .await
+    {
+        Ok(result) => Json(result).into_response(),
+        Err(err) => api_error_response(StatusCode::BAD_GATEWAY, "Search failed", err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AutocompleteParams {
+    #[serde(default)]
+    q: String,
+    domain: Option<u8>,
+    limit: Option<u32>,
+}
+
+/**
+ * GET /api/products/autocomplete?q=&domain=&limit=
+ */
+pub async fn autocomplete_products(
+    State(state): State<AppState>,
+    Query(params): Query<AutocompleteParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+
+    match ProductAutocomplete::new(&state.index_repository)
+        .autocomplete(
+            &state.index_definition_loader,
+            domain,
+            &params.q,
+            params
+                .limit
+                .unwrap_or(AUTOCOMPLETE_PRODUCTS_LIMIT)
+                .min(AUTOCOMPLETE_PRODUCTS_MAX_LIMIT),
+        )
+        .await
+    {
+        Ok(result) => Json(result).into_response(),
+        Err(err) => api_error_response(StatusCode::BAD_GATEWAY, "Autocomplete failed", err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CategorySearchParams {
+    #[serde(default)]
+    q: String,
+    domain: Option<u8>,
+    limit: Option<u32>,
+}
+
+/**
+ * GET /api/categories/search?q=&domain=&limit=
+ */
+pub async fn search_categories(
+    State(state): State<AppState>,
+    Query(params): Query<CategorySearchParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+    let index_definition = state.get_index_definition("category", domain.id);
+
+    match CategorySearch::new(&state.index_repository)
+        .search(
+            &index_definition,
+            &params.q,
+            params
+                .limit
+                .unwrap_or(CATEGORY_SEARCH_LIMIT)
+                .min(CATEGORY_SEARCH_MAX_LIMIT),
+        )
+        .await
+    {
+        Ok(result) => Json(result).into_response(),
+        Err(err) => api_error_response(StatusCode::BAD_GATEWAY, "Category search failed", err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArticleSearchParams {
+    #[serde(default)]
+    q: String,
+    domain: Option<u8>,
+    limit: Option<u32>,
+}
+
+/**
+ * GET /api/articles/search?q=&domain=&limit=
+ */
+pub async fn search_articles(
+    State(state): State<AppState>,
+    Query(params): Query<ArticleSearchParams>,
+) -> Response {
+    let Some(domain) = state.get_domain(params.domain.unwrap_or(1)) else {
+        return DOMAIN_NOT_FOUND.into_response();
+    };
+    let index_definition = state.get_index_definition("article", domain.id);
+
+    match ArticleSearch::new(&state.index_repository)
+        .search(
+            &index_definition,
+            &params.q,
+            params
+                .limit
+                .unwrap_or(ARTICLE_SEARCH_LIMIT)
+                .min(ARTICLE_SEARCH_MAX_LIMIT),
+        )
+        .await
+    {
+        Ok(result) => Json(result).into_response(),
+        Err(err) => api_error_response(StatusCode::BAD_GATEWAY, "Article search failed", err),
+    }
+}
+
+fn parse_ids(ids: &str) -> Vec<i32> {
+    ids.split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
@@ -0,0 +1,646 @@
+use shopsys_sqlx_test::domain::domain_config::DomainConfig;
+use sqlx::{PgPool, Postgres, Transaction};
+
+pub const ROOT_CATEGORY_ID: i32 = 1;
+
+/**
+ * Rows of the Shopsys tables from migrations/ for tests, inserted by FixtureBuilder::insert in one transaction
+ *
+ * Every product and category is on all domains of the builder. Ids are explicit, so the tests can refer to them,
+ * the root category has id 1 and the tree (level, lft, rgt) is computed from parents in the order of adding.
+ * Tests with #[sqlx::test] get a throwaway database on the server of DATABASE_URL with migrations/ applied.
+ * They are ignored by default, run them with a running Postgres by
+ * DATABASE_URL=postgres://postgres@127.0.0.1/shopsys cargo test -- --ignored
+ */
+pub struct FixtureBuilder {
+    domains: Vec<DomainConfig>,
+    brands: Vec<(i32, String)>,
+    /// (id, dispatch_time, names)
+    availabilities: Vec<(i32, i32, LocalizedNames)>,
+    parameters: Vec<(i32, LocalizedNames)>,
+    categories: Vec<CategoryFixture>,
+    products: Vec<ProductFixture>,
+}
+
+/// (locale, name)
+type LocalizedNames = Vec<(String, String)>;
+
+pub struct CategoryFixture {
+    id: i32,
+    parent_id: i32,
+    names: Vec<(String, String)>,
+    slug: Option<String>,
+    hidden_domain_ids: Vec<u8>,
+}
+
+pub struct ProductFixture {
+    id: i32,
+    catnum: String,
+    partno: Option<String>,
+    ean: Option<String>,
+    brand_id: Option<i32>,
+    main_variant_id: Option<i32>,
+    is_selling_denied: bool,
+    translations: Vec<ProductTranslationFixture>,
+    description: Option<String>,
+    short_description: Option<String>,
+    flag_ids: Vec<i32>,
+    category_ids: Vec<i32>,
+    hidden_domain_ids: Vec<u8>,
+    slug: Option<String>,
+    /// (image id, extension)
+    images: Vec<(i32, String)>,
+    price_with_vat: Option<f64>,
+    availability_id: Option<i32>,
+    /// (parameter_id, locale, text)
+    parameter_values: Vec<(i32, String, String)>,
+}
+
+struct ProductTranslationFixture {
+    locale: String,
+    name: String,
+    name_prefix: Option<String>,
+    name_sufix: Option<String>,
+}
+
+impl Default for FixtureBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FixtureBuilder {
+    /**
+     * Czech domain 1 and English domain 2, as in the Shopsys demo data
+     */
+    pub fn new() -> Self {
+        FixtureBuilder {
+            domains: vec![
+                DomainConfig {
+                    id: 1,
+                    name: "Shopsys CZ".to_string(),
+                    locale: "cs".to_string(),
+                    pricing_group_id: 1,
+                },
+                DomainConfig {
+                    id: 2,
+                    name: "Shopsys EN".to_string(),
+                    locale: "en".to_string(),
+                    pricing_group_id: 2,
+                },
+            ],
+            brands: vec![],
+            availabilities: vec![],
+            parameters: vec![],
+            categories: vec![],
+            products: vec![],
+        }
+    }
+
+    pub fn get_domains(&self) -> Vec<DomainConfig> {
+        self.domains.clone()
+    }
+
+    pub fn brand(mut self, id: i32, name: &str) -> Self {
+        self.brands.push((id, name.to_string()));
+        self
+    }
+
+    pub fn availability(mut self, id: i32, dispatch_time: i32, names: &[(&str, &str)]) -> Self {
+        self.availabilities
+            .push((id, dispatch_time, get_localized(names)));
+        self
+    }
+
+    pub fn parameter(mut self, id: i32, names: &[(&str, &str)]) -> Self {
+        self.parameters.push((id, get_localized(names)));
+        self
+    }
+
+    pub fn category(mut self, category: CategoryFixture) -> Self {
+        self.categories.push(category);
+        self
+    }
+
+    pub fn product(mut self, product: ProductFixture) -> Self {
+        self.products.push(product);
+        self
+    }
+
+    pub async fn insert(self, pool: &PgPool) {
+        let mut transaction = pool.begin().await.unwrap();
+
+        for (id, name) in &self.brands {
+            sqlx::query("INSERT INTO brands (id, name) VALUES ($1, $2)")
+                .bind(id)
+                .bind(name)
+                .execute(&mut *transaction)
+                .await
+                .unwrap();
+        }
+        self.insert_pricing_groups_availabilities_and_parameters(&mut transaction)
+            .await;
+        self.insert_categories(&mut transaction).await;
+        self.insert_products(&mut transaction).await;
+
+        transaction.commit().await.unwrap();
+    }
+
+    async fn insert_pricing_groups_availabilities_and_parameters(
+        &self,
+        transaction: &mut Transaction<'static, Postgres>,
+    ) {
+        for domain in &self.domains {
+            sqlx::query("INSERT INTO pricing_groups (id, name, domain_id) VALUES ($1, $2, $3)")
+                .bind(domain.pricing_group_id)
+                .bind(format!("Ordinary customer {}", domain.id))
+                .bind(domain.id as i32)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+        }
+
+        for (id, dispatch_time, names) in &self.availabilities {
+            sqlx::query("INSERT INTO availabilities (id, dispatch_time) VALUES ($1, $2)")
+                .bind(id)
+                .bind(dispatch_time)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            for (locale, name) in names {
+                sqlx::query(
+                    "INSERT INTO availability_translations (translatable_id, locale, name) VALUES ($1, $2, $3)",
+                )
+                .bind(id)
+                .bind(locale)
+                .bind(name)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            }
+        }
+
+        for (id, names) in &self.parameters {
+            sqlx::query("INSERT INTO parameters (id, uuid) VALUES ($1, gen_random_uuid())")
+                .bind(id)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            for (locale, name) in names {
+                sqlx::query(
+                    "INSERT INTO parameter_translations (translatable_id, locale, name) VALUES ($1, $2, $3)",
+                )
+                .bind(id)
+                .bind(locale)
+                .bind(name)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            }
+        }
+    }
+
+    async fn insert_categories(&self, transaction: &mut Transaction<'static, Postgres>) {
+        let mut nested_set = vec![];
+        let mut next_value = 1;
+        self.compute_nested_set(ROOT_CATEGORY_ID, None, 0, &mut next_value, &mut nested_set);
+
+        for (id, parent_id, level, lft, rgt) in nested_set {
+            sqlx::query(
+                "INSERT INTO categories (id, parent_id, level, lft, rgt) VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(id)
+            .bind(parent_id)
+            .bind(level)
+            .bind(lft)
+            .bind(rgt)
+            .execute(&mut **transaction)
+            .await
+            .unwrap();
+        }
+
+        for category in &self.categories {
+            for (locale, name) in &category.names {
+                sqlx::query(
+                    "INSERT INTO category_translations (translatable_id, locale, name) VALUES ($1, $2, $3)",
+                )
+                .bind(category.id)
+                .bind(locale)
+                .bind(name)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            }
+        }
+
+        let root_category = CategoryFixture::new(ROOT_CATEGORY_ID, 0);
+        for category in std::iter::once(&root_category).chain(&self.categories) {
+            for domain in &self.domains {
+                sqlx::query(
+                    "INSERT INTO category_domains (category_id, domain_id, visible) VALUES ($1, $2, $3)",
+                )
+                .bind(category.id)
+                .bind(domain.id as i32)
+                .bind(!category.hidden_domain_ids.contains(&domain.id))
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+
+                if let Some(slug) = &category.slug {
+                    sqlx::query(
+                        "INSERT INTO friendly_urls (domain_id, slug, route_name, entity_id, main) VALUES ($1, $2, 'front_product_list', $3, TRUE)",
+                    )
+                    .bind(domain.id as i32)
+                    .bind(slug)
+                    .bind(category.id)
+                    .execute(&mut **transaction)
+                    .await
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    /**
+     * Rows (id, parent_id, level, lft, rgt) of the category and its subtree
+     */
+    fn compute_nested_set(
+        &self,
+        id: i32,
+        parent_id: Option<i32>,
+        level: i32,
+        next_value: &mut i32,
+        nested_set: &mut Vec<(i32, Option<i32>, i32, i32, i32)>,
+    ) {
+        let position = nested_set.len();
+        nested_set.push((id, parent_id, level, *next_value, 0));
+        *next_value += 1;
+
+        for category in self
+            .categories
+            .iter()
+            .filter(|category| category.parent_id == id)
+        {
+            self.compute_nested_set(category.id, Some(id), level + 1, next_value, nested_set);
+        }
+
+        nested_set[position].4 = *next_value;
+        *next_value += 1;
+    }
+
+    async fn insert_products(&self, transaction: &mut Transaction<'static, Postgres>) {
+        // main variants have to be inserted before their variants because of the foreign key
+        let mut products: Vec<&ProductFixture> = self.products.iter().collect();
+        products.sort_by_key(|product| product.main_variant_id.is_some());
+
+        for product in products {
+            let variant_type = match product.main_variant_id {
+                Some(_) => "variant",
+                None if self.is_main_variant(product.id) => "main",
+                None => "none",
+            };
+            sqlx::query(
+                r#"INSERT INTO products (id, catnum, partno, ean, brand_id, variant_type, main_variant_id, calculated_selling_denied)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+            )
+            .bind(product.id)
+            .bind(&product.catnum)
+            .bind(&product.partno)
+            .bind(&product.ean)
+            .bind(product.brand_id)
+            .bind(variant_type)
+            .bind(product.main_variant_id)
+            .bind(product.is_selling_denied)
+            .execute(&mut **transaction)
+            .await
+            .unwrap();
+
+            if let Some(availability_id) = product.availability_id {
+                sqlx::query("UPDATE products SET calculated_availability_id = $1 WHERE id = $2")
+                    .bind(availability_id)
+                    .bind(product.id)
+                    .execute(&mut **transaction)
+                    .await
+                    .unwrap();
+            }
+
+            for (position, (image_id, extension)) in product.images.iter().enumerate() {
+                sqlx::query(
+                    "INSERT INTO images (id, entity_name, entity_id, extension, position) VALUES ($1, 'product', $2, $3, $4)",
+                )
+                .bind(image_id)
+                .bind(product.id)
+                .bind(extension)
+                .bind(position as i32)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            }
+
+            for (parameter_id, locale, text) in &product.parameter_values {
+                sqlx::query(
+                    r#"WITH value AS (
+                        INSERT INTO parameter_values (uuid, locale, text) VALUES (gen_random_uuid(), $3, $4) RETURNING id
+                    )
+                    INSERT INTO product_parameter_values (product_id, parameter_id, value_id) SELECT $1, $2, id FROM value"#,
+                )
+                .bind(product.id)
+                .bind(parameter_id)
+                .bind(locale)
+                .bind(text)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            }
+
+            for translation in &product.translations {
+                sqlx::query(
+                    "INSERT INTO product_translations (translatable_id, locale, name, name_prefix, name_sufix) VALUES ($1, $2, $3, $4, $5)",
+                )
+                .bind(product.id)
+                .bind(&translation.locale)
+                .bind(&translation.name)
+                .bind(&translation.name_prefix)
+                .bind(&translation.name_sufix)
+                .execute(&mut **transaction)
+                .await
+                .unwrap();
+            }
+
+            for domain in &self.domains {
+                self.insert_product_domain(transaction, product, domain)
+                    .await;
+            }
+        }
+    }
+
+    async fn insert_product_domain(
+        &self,
+        transaction: &mut Transaction<'static, Postgres>,
+        product: &ProductFixture,
+        domain: &DomainConfig,
+    ) {
+        let domain_id = domain.id as i32;
+        let product_domain_id: i32 = sqlx::query_scalar(
+            "INSERT INTO product_domains (product_id, domain_id, description, short_description) VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(product.id)
+        .bind(domain_id)
+        .bind(&product.description)
+        .bind(&product.short_description)
+        .fetch_one(&mut **transaction)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            "INSERT INTO product_visibilities (product_id, pricing_group_id, domain_id, visible) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(product.id)
+        .bind(domain.pricing_group_id)
+        .bind(domain_id)
+        .bind(!product.hidden_domain_ids.contains(&(domain_id as u8)))
+        .execute(&mut **transaction)
+        .await
+        .unwrap();
+
+        if let Some(price_with_vat) = product.price_with_vat {
+            sqlx::query(
+                "INSERT INTO product_calculated_prices (product_id, pricing_group_id, price_with_vat) VALUES ($1, $2, $3::NUMERIC)",
+            )
+            .bind(product.id)
+            .bind(domain.pricing_group_id)
+            .bind(price_with_vat)
+            .execute(&mut **transaction)
+            .await
+            .unwrap();
+        }
+
+        if let Some(slug) = &product.slug {
+            sqlx::query(
+                "INSERT INTO friendly_urls (domain_id, slug, route_name, entity_id, main) VALUES ($1, $2, 'front_product_detail', $3, TRUE)",
+            )
+            .bind(domain_id)
+            .bind(slug)
+            .bind(product.id)
+            .execute(&mut **transaction)
+            .await
+            .unwrap();
+        }
+
+        for flag_id in &product.flag_ids {
+            sqlx::query(
+                "INSERT INTO product_domain_flags (product_domain_id, flag_id) VALUES ($1, $2)",
+            )
+            .bind(product_domain_id)
+            .bind(flag_id)
+            .execute(&mut **transaction)
+            .await
+            .unwrap();
+        }
+
+        for category_id in &product.category_ids {
+            sqlx::query(
+                "INSERT INTO product_category_domains (product_id, category_id, domain_id) VALUES ($1, $2, $3)",
+            )
+            .bind(product.id)
+            .bind(category_id)
+            .bind(domain_id)
+            .execute(&mut **transaction)
+            .await
+            .unwrap();
+        }
+    }
+
+    fn is_main_variant(&self, product_id: i32) -> bool {
+        self.products
+            .iter()
+            .any(|product| product.main_variant_id == Some(product_id))
+    }
+}
+
+impl CategoryFixture {
+    /**
+     * Category under the parent, use ROOT_CATEGORY_ID for the top level
+     */
+    pub fn new(id: i32, parent_id: i32) -> Self {
+        CategoryFixture {
+            id,
+            parent_id,
+            names: vec![],
+            slug: None,
+            hidden_domain_ids: vec![],
+        }
+    }
+
+    pub fn name(mut self, locale: &str, name: &str) -> Self {
+        self.names.push((locale.to_string(), name.to_string()));
+        self
+    }
+
+    /**
+     * Main friendly url of the category on all domains
+     */
+    pub fn slug(mut self, slug: &str) -> Self {
+        self.slug = Some(slug.to_string());
+        self
+    }
+
+    pub fn hidden_on(mut self, domain_id: u8) -> Self {
+        self.hidden_domain_ids.push(domain_id);
+        self
+    }
+}
+
+impl ProductFixture {
+    pub fn new(id: i32) -> Self {
+        ProductFixture {
+            id,
+            catnum: format!("CAT{id}"),
+            partno: None,
+            ean: None,
+            brand_id: None,
+            main_variant_id: None,
+            is_selling_denied: false,
+            translations: vec![],
+            description: None,
+            short_description: None,
+            flag_ids: vec![],
+            category_ids: vec![],
+            hidden_domain_ids: vec![],
+            slug: None,
+            images: vec![],
+            price_with_vat: None,
+            availability_id: None,
+            parameter_values: vec![],
+        }
+    }
+
+    pub fn partno(mut self, partno: &str) -> Self {
+        self.partno = Some(partno.to_string());
+        self
+    }
+
+    pub fn ean(mut self, ean: &str) -> Self {
+        self.ean = Some(ean.to_string());
+        self
+    }
+
+    pub fn name(mut self, locale: &str, name: &str) -> Self {
+        self.translations.push(ProductTranslationFixture {
+            locale: locale.to_string(),
+            name: name.to_string(),
+            name_prefix: None,
+            name_sufix: None,
+        });
+        self
+    }
+
+    /**
+     * Name with the prefix and suffix, from which the full name is exported
+     */
+    pub fn full_name(
+        mut self,
+        locale: &str,
+        name_prefix: &str,
+        name: &str,
+        name_sufix: &str,
+    ) -> Self {
+        self.translations.push(ProductTranslationFixture {
+            locale: locale.to_string(),
+            name: name.to_string(),
+            name_prefix: Some(name_prefix.to_string()),
+            name_sufix: Some(name_sufix.to_string()),
+        });
+        self
+    }
+
+    /**
+     * Description and short description on all domains
+     */
+    pub fn description(mut self, description: &str, short_description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self.short_description = Some(short_description.to_string());
+        self
+    }
+
+    pub fn brand(mut self, brand_id: i32) -> Self {
+        self.brand_id = Some(brand_id);
+        self
+    }
+
+    pub fn flags(mut self, flag_ids: &[i32]) -> Self {
+        self.flag_ids = flag_ids.to_vec();
+        self
+    }
+
+    pub fn categories(mut self, category_ids: &[i32]) -> Self {
+        self.category_ids = category_ids.to_vec();
+        self
+    }
+
+    /**
+     * Variant of the main variant, which gets variant_type "main" automatically
+     */
+    pub fn variant_of(mut self, main_variant_id: i32) -> Self {
+        self.main_variant_id = Some(main_variant_id);
+        self
+    }
+
+    /**
+     * Main friendly url of the product on all domains
+     */
+    pub fn slug(mut self, slug: &str) -> Self {
+        self.slug = Some(slug.to_string());
+        self
+    }
+
+    /**
+     * Image of the product, images are positioned in the order they are added
+     */
+    pub fn image(mut self, image_id: i32, extension: &str) -> Self {
+        self.images.push((image_id, extension.to_string()));
+        self
+    }
+
+    /**
+     * Calculated price for the pricing group of every domain
+     */
+    pub fn price(mut self, price_with_vat: f64) -> Self {
+        self.price_with_vat = Some(price_with_vat);
+        self
+    }
+
+    pub fn availability(mut self, availability_id: i32) -> Self {
+        self.availability_id = Some(availability_id);
+        self
+    }
+
+    /**
+     * Value of the parameter in the locale, the value row is created for the product
+     */
+    pub fn parameter_value(mut self, parameter_id: i32, locale: &str, text: &str) -> Self {
+        self.parameter_values
+            .push((parameter_id, locale.to_string(), text.to_string()));
+        self
+    }
+
+    pub fn selling_denied(mut self) -> Self {
+        self.is_selling_denied = true;
+        self
+    }
+
+    /**
+     * Not visible for the default pricing group on the domain
+     */
+    pub fn hidden_on(mut self, domain_id: u8) -> Self {
+        self.hidden_domain_ids.push(domain_id);
+        self
+    }
+}
+
+fn get_localized(names: &[(&str, &str)]) -> LocalizedNames {
+    names
+        .iter()
+        .map(|(locale, name)| (locale.to_string(), name.to_string()))
+        .collect()
+}
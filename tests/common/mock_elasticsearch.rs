@@ -0,0 +1,585 @@
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use elasticsearch::http::transport::Transport;
+use elasticsearch::Elasticsearch;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+/**
+ * Elasticsearch on a random local port with indexes kept in memory, so IndexRepository can be tested offline
+ *
+ * Only the endpoints called by IndexRepository are implemented: indices exists/create/delete, alias get/put/exists,
+ * _aliases, _cat/indices, _reindex, _tasks, _bulk, _mget and _search of all documents with scroll. Mappings are stored
+ * but not applied, documents are kept as sent. Tasks of _reindex with wait_for_completion=false are completed right away.
+ */
+pub struct MockElasticsearch {
+    url: String,
+    state: SharedState,
+}
+
+type SharedState = Arc<Mutex<MockState>>;
+
+#[derive(Default)]
+struct MockState {
+    indexes: BTreeMap<String, MockIndex>,
+    tasks: BTreeMap<String, Value>,
+    is_reindex_failing: bool,
+    /// scroll id => (page size, ids of the following pages)
+    scrolls: BTreeMap<String, (usize, Vec<String>)>,
+    /// operations of all _bulk requests, e.g. to check that no document is sent twice
+    bulk_operation_count: usize,
+}
+
+#[derive(Default)]
+struct MockIndex {
+    definition: Value,
+    aliases: BTreeSet<String>,
+    documents: BTreeMap<String, Value>,
+}
+
+impl MockState {
+    /**
+     * Index of the name or the first index behind the alias
+     */
+    fn resolve(&self, name: &str) -> Option<String> {
+        match self.indexes.contains_key(name) {
+            true => Some(name.to_string()),
+            false => self
+                .indexes
+                .iter()
+                .find(|(_, index)| index.aliases.contains(name))
+                .map(|(index_name, _)| index_name.clone()),
+        }
+    }
+
+    fn is_alias_created(&self, alias: &str) -> bool {
+        self.indexes
+            .values()
+            .any(|index| index.aliases.contains(alias))
+    }
+}
+
+impl MockElasticsearch {
+    pub async fn start() -> Self {
+        let state = SharedState::default();
+        let app = Router::new().fallback(handle).with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        MockElasticsearch { url, state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn client(&self) -> Elasticsearch {
+        Elasticsearch::new(Transport::single_node(&self.url).unwrap())
+    }
+
+    /**
+     * Index that already exists in the cluster, e.g. one created by an older definition
+     */
+    pub fn add_index(&self, index_name: &str, aliases: &[&str], documents: Vec<(i32, Value)>) {
+        self.state.lock().unwrap().indexes.insert(
+            index_name.to_string(),
+            MockIndex {
+                definition: json!({}),
+                aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+                documents: documents
+                    .into_iter()
+                    .map(|(id, document)| (id.to_string(), document))
+                    .collect(),
+            },
+        );
+    }
+
+    /**
+     * Following reindexes copy no documents and report a failure, as when the new mapping rejects them
+     */
+    pub fn fail_reindex(&self) {
+        self.state.lock().unwrap().is_reindex_failing = true;
+    }
+
+    pub fn get_index_names(&self) -> Vec<String> {
+        self.state.lock().unwrap().indexes.keys().cloned().collect()
+    }
+
+    pub fn get_aliases(&self, index_name: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .indexes
+            .get(index_name)
+            .map(|index| index.aliases.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /**
+     * Body of the create index request
+     */
+    pub fn get_definition(&self, index_name: &str) -> Option<Value> {
+        self.state
+            .lock()
+            .unwrap()
+            .indexes
+            .get(index_name)
+            .map(|index| index.definition.clone())
+    }
+
+    pub fn get_bulk_operation_count(&self) -> usize {
+        self.state.lock().unwrap().bulk_operation_count
+    }
+
+    /**
+     * Documents of the index or of the index behind the alias, by id
+     */
+    pub fn get_documents(&self, name: &str) -> BTreeMap<i32, Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .resolve(name)
+            .and_then(|index_name| state.indexes.get(&index_name))
+            .map(|index| {
+                index
+                    .documents
+                    .iter()
+                    .map(|(id, document)| (id.parse().unwrap(), document.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+async fn handle(
+    State(state): State<SharedState>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    let segments: Vec<&str> = uri.path().trim_matches('/').split('/').collect();
+    let body = String::from_utf8_lossy(&body);
+    let mut state = state.lock().unwrap();
+
+    match (&method, segments.as_slice()) {
+        (&Method::HEAD, ["_alias", alias]) => match state.is_alias_created(alias) {
+            true => StatusCode::OK.into_response(),
+            false => StatusCode::NOT_FOUND.into_response(),
+        },
+        (&Method::GET, ["_alias"]) => get_aliases(&state, None),
+        (&Method::GET, ["_alias", alias]) => get_aliases(&state, Some(alias)),
+        (&Method::GET, ["_cat", "indices", ..]) => cat_indices(&state, segments.get(2).copied()),
+        (&Method::POST, ["_aliases"]) => update_aliases(&mut state, &body),
+        (&Method::POST, ["_reindex"]) => {
+            let wait_for_completion = !uri
+                .query()
+                .unwrap_or_default()
+                .contains("wait_for_completion=false");
+            reindex(&mut state, &body, wait_for_completion)
+        }
+        // client encodes the colon of node:id
+        (&Method::GET, ["_tasks", task_id]) => {
+            match state.tasks.get(&task_id.replace("%3A", ":")) {
+                Some(task_response) => (
+                    StatusCode::OK,
+                    Json(json!({ "completed": true, "response": task_response })),
+                )
+                    .into_response(),
+                None => error_response(
+                    StatusCode::NOT_FOUND,
+                    "resource_not_found_exception",
+                    &format!("task [{task_id}] isn't running and hasn't stored its results"),
+                ),
+            }
+        }
+        (&Method::GET | &Method::POST, [name, "_mget"]) => mget(&state, name, &body),
+        (&Method::GET | &Method::POST, [name, "_search"]) => {
+            let is_scroll = uri.query().unwrap_or_default().contains("scroll=");
+            search(&mut state, name, &body, is_scroll)
+        }
+        (&Method::GET | &Method::POST, ["_search", "scroll"]) => scroll(&mut state, &body),
+        (&Method::DELETE, ["_search", "scroll"]) => {
+            let request: Value = serde_json::from_str(&body).unwrap_or_default();
+            for scroll_id in request["scroll_id"].as_array().into_iter().flatten() {
+                state.scrolls.remove(scroll_id.as_str().unwrap_or_default());
+            }
+            (StatusCode::OK, Json(json!({ "succeeded": true }))).into_response()
+        }
+        (&Method::POST | &Method::PUT, ["_bulk"]) => bulk(&mut state, None, &body),
+        (&Method::POST | &Method::PUT, [name, "_bulk"]) => bulk(&mut state, Some(name), &body),
+        (&Method::PUT | &Method::POST, [index_name, "_alias" | "_aliases", alias]) => {
+            match state.indexes.get_mut(*index_name) {
+                Some(index) => {
+                    index.aliases.insert(alias.to_string());
+                    acknowledged()
+                }
+                None => index_not_found(index_name),
+            }
+        }
+        (&Method::HEAD, [index_name]) => match state.indexes.contains_key(*index_name) {
+            true => StatusCode::OK.into_response(),
+            false => StatusCode::NOT_FOUND.into_response(),
+        },
+        (&Method::PUT, [index_name]) => create_index(&mut state, index_name, &body),
+        (&Method::DELETE, [index_names]) => {
+            for index_name in index_names.split(',') {
+                if state.indexes.remove(index_name).is_none() {
+                    return index_not_found(index_name);
+                }
+            }
+            acknowledged()
+        }
+        _ => error_response(
+            StatusCode::BAD_REQUEST,
+            "mock_not_implemented_exception",
+            &format!("{} {} is not implemented by the mock", method, uri),
+        ),
+    }
+}
+
+fn create_index(state: &mut MockState, index_name: &str, body: &str) -> Response {
+    if state.resolve(index_name).is_some() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "resource_already_exists_exception",
+            &format!("index [{index_name}] already exists"),
+        );
+    }
+    let definition = match body.is_empty() {
+        true => json!({}),
+        false => match serde_json::from_str(body) {
+            Ok(definition) => definition,
+            Err(err) => {
+                return error_response(StatusCode::BAD_REQUEST, "parse_exception", &err.to_string())
+            }
+        },
+    };
+
+    state.indexes.insert(
+        index_name.to_string(),
+        MockIndex {
+            definition,
+            ..Default::default()
+        },
+    );
+    (
+        StatusCode::OK,
+        Json(json!({ "acknowledged": true, "shards_acknowledged": true, "index": index_name })),
+    )
+        .into_response()
+}
+
+/**
+ * Index name => {"aliases": {alias: {}}}, only indexes with the alias when it is given
+ */
+fn get_aliases(state: &MockState, alias: Option<&str>) -> Response {
+    let aliases_by_index: Map<String, Value> = state
+        .indexes
+        .iter()
+        .filter(|(_, index)| alias.is_none_or(|alias| index.aliases.contains(alias)))
+        .map(|(index_name, index)| {
+            let aliases: Map<String, Value> = index
+                .aliases
+                .iter()
+                .filter(|index_alias| alias.is_none_or(|alias| alias == *index_alias))
+                .map(|index_alias| (index_alias.clone(), json!({})))
+                .collect();
+            (index_name.clone(), json!({ "aliases": aliases }))
+        })
+        .collect();
+
+    match alias.is_some() && aliases_by_index.is_empty() {
+        true => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("alias [{}] missing", alias.unwrap()), "status": 404 })),
+        )
+            .into_response(),
+        false => (StatusCode::OK, Json(Value::Object(aliases_by_index))).into_response(),
+    }
+}
+
+/**
+ * Records of format=json&bytes=b, the store size is made up from the number of documents
+ */
+fn cat_indices(state: &MockState, pattern: Option<&str>) -> Response {
+    let records: Vec<Value> = state
+        .indexes
+        .iter()
+        .filter(|(index_name, _)| is_matching(index_name, pattern.unwrap_or("*")))
+        .map(|(index_name, index)| {
+            json!({
+                "health": "green",
+                "status": "open",
+                "index": index_name,
+                "docs.count": index.documents.len().to_string(),
+                "store.size": (208 + 1000 * index.documents.len()).to_string(),
+            })
+        })
+        .collect();
+
+    (StatusCode::OK, Json(json!(records))).into_response()
+}
+
+fn is_matching(index_name: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => index_name.starts_with(prefix),
+        None => index_name == pattern,
+    }
+}
+
+fn update_aliases(state: &mut MockState, body: &str) -> Response {
+    let request: Value = serde_json::from_str(body).unwrap_or_default();
+    for action in request["actions"].as_array().into_iter().flatten() {
+        let (operation, parameters) =
+            match action.as_object().and_then(|action| action.iter().next()) {
+                Some(operation) => operation,
+                None => continue,
+            };
+        let index_name = parameters["index"].as_str().unwrap_or_default();
+        let alias = parameters["alias"].as_str().unwrap_or_default().to_string();
+        let Some(index) = state.indexes.get_mut(index_name) else {
+            return index_not_found(index_name);
+        };
+        match operation.as_str() {
+            "add" => {
+                index.aliases.insert(alias);
+            }
+            "remove" => {
+                index.aliases.remove(&alias);
+            }
+            _ => {}
+        }
+    }
+
+    acknowledged()
+}
+
+fn reindex(state: &mut MockState, body: &str, wait_for_completion: bool) -> Response {
+    let request: Value = serde_json::from_str(body).unwrap_or_default();
+    let source = request["source"]["index"].as_str().unwrap_or_default();
+    let destination = request["dest"]["index"].as_str().unwrap_or_default();
+
+    let Some(documents) = state
+        .resolve(source)
+        .and_then(|index_name| state.indexes.get(&index_name))
+        .map(|index| index.documents.clone())
+    else {
+        return index_not_found(source);
+    };
+    let total = documents.len();
+    let (created, failures) = match state.is_reindex_failing {
+        true => (
+            0,
+            documents
+                .keys()
+                .map(|id| json!({ "index": destination, "id": id, "status": 400 }))
+                .collect(),
+        ),
+        false => {
+            state
+                .indexes
+                .entry(destination.to_string())
+                .or_default()
+                .documents
+                .extend(documents);
+            (total, vec![])
+        }
+    };
+    let response =
+        json!({ "total": total, "created": created, "updated": 0, "failures": failures });
+
+    match wait_for_completion {
+        true => (StatusCode::OK, Json(response)).into_response(),
+        false => {
+            let task_id = format!("mock:{}", state.tasks.len() + 1);
+            state.tasks.insert(task_id.clone(), response);
+            (StatusCode::OK, Json(json!({ "task": task_id }))).into_response()
+        }
+    }
+}
+
+fn mget(state: &MockState, name: &str, body: &str) -> Response {
+    let request: Value = serde_json::from_str(body).unwrap_or_default();
+    let Some(index_name) = state.resolve(name) else {
+        return index_not_found(name);
+    };
+    let documents = &state.indexes[&index_name].documents;
+    let docs: Vec<Value> = request["ids"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|id| {
+            let id = id
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| id.to_string());
+            match documents.get(&id) {
+                Some(document) => {
+                    json!({ "_index": index_name, "_id": id, "found": true, "_source": document })
+                }
+                None => json!({ "_index": index_name, "_id": id, "found": false }),
+            }
+        })
+        .collect();
+
+    (StatusCode::OK, Json(json!({ "docs": docs }))).into_response()
+}
+
+/**
+ * Query is ignored, all documents are found in the order of their ids, the rest over the size is left for the scroll
+ */
+fn search(state: &mut MockState, name: &str, body: &str, is_scroll: bool) -> Response {
+    let request: Value = serde_json::from_str(body).unwrap_or_default();
+    let Some(index_name) = state.resolve(name) else {
+        return index_not_found(name);
+    };
+    let mut ids: Vec<String> = state.indexes[&index_name]
+        .documents
+        .keys()
+        .cloned()
+        .collect();
+    let size = request["size"].as_u64().unwrap_or(10) as usize;
+    let rest = ids.split_off(size.min(ids.len()));
+
+    let mut response = get_search_response(&index_name, ids, rest.len());
+    if is_scroll {
+        let scroll_id = format!("mock_scroll_{}", state.scrolls.len() + 1);
+        state.scrolls.insert(scroll_id.clone(), (size, rest));
+        response["_scroll_id"] = json!(scroll_id);
+    }
+
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+fn scroll(state: &mut MockState, body: &str) -> Response {
+    let request: Value = serde_json::from_str(body).unwrap_or_default();
+    let scroll_id = request["scroll_id"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let Some((size, ids)) = state.scrolls.get_mut(&scroll_id) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "search_context_missing_exception",
+            &format!("No search context found for id [{scroll_id}]"),
+        );
+    };
+    let page: Vec<String> = ids.drain(..ids.len().min(*size)).collect();
+    let rest_count = ids.len();
+
+    let mut response = get_search_response("", page, rest_count);
+    response["_scroll_id"] = json!(scroll_id);
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+fn get_search_response(index_name: &str, ids: Vec<String>, rest_count: usize) -> Value {
+    let total = ids.len() + rest_count;
+    let hits: Vec<Value> = ids
+        .into_iter()
+        .map(|id| json!({ "_index": index_name, "_id": id, "_score": null }))
+        .collect();
+
+    json!({ "took": 1, "timed_out": false, "hits": { "total": { "value": total, "relation": "eq" }, "hits": hits } })
+}
+
+/**
+ * NDJSON of index/create/update/delete actions, missing index is created as by the dynamic mapping
+ */
+fn bulk(state: &mut MockState, default_index: Option<&str>, body: &str) -> Response {
+    let mut lines = body.lines().filter(|line| !line.trim().is_empty());
+    let mut items = vec![];
+    let mut has_errors = false;
+
+    while let Some(line) = lines.next() {
+        let action: Value = serde_json::from_str(line).unwrap_or_default();
+        let Some((operation, metadata)) =
+            action.as_object().and_then(|action| action.iter().next())
+        else {
+            continue;
+        };
+        let name = metadata["_index"]
+            .as_str()
+            .or(default_index)
+            .unwrap_or_default()
+            .to_string();
+        let id = match &metadata["_id"] {
+            Value::String(id) => id.clone(),
+            id => id.to_string(),
+        };
+        let source: Value = match operation.as_str() {
+            "delete" => Value::Null,
+            _ => serde_json::from_str(lines.next().unwrap_or_default()).unwrap_or_default(),
+        };
+
+        let index_name = state.resolve(&name).unwrap_or_else(|| name.clone());
+        let documents = &mut state
+            .indexes
+            .entry(index_name.clone())
+            .or_default()
+            .documents;
+        let (status, error) = match operation.as_str() {
+            "index" | "create" => (
+                documents.insert(id.clone(), source).map_or(201, |_| 200),
+                None,
+            ),
+            "update" => match (documents.get_mut(&id), source["doc_as_upsert"].as_bool()) {
+                (Some(Value::Object(document)), _) => {
+                    if let Some(doc) = source["doc"].as_object() {
+                        document.extend(doc.clone());
+                    }
+                    (200, None)
+                }
+                (_, Some(true)) => {
+                    documents.insert(id.clone(), source["doc"].clone());
+                    (201, None)
+                }
+                _ => (404, Some("document_missing_exception")),
+            },
+            "delete" => (documents.remove(&id).map_or(404, |_| 200), None),
+            _ => (400, Some("illegal_argument_exception")),
+        };
+
+        let mut item = json!({ "_index": index_name, "_id": id, "status": status });
+        if let Some(error_type) = error {
+            has_errors = true;
+            item["error"] =
+                json!({ "type": error_type, "reason": format!("[{id}]: {error_type}") });
+        }
+        items.push(json!({ operation.clone(): item }));
+        state.bulk_operation_count += 1;
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({ "took": 1, "errors": has_errors, "items": items })),
+    )
+        .into_response()
+}
+
+fn acknowledged() -> Response {
+    (StatusCode::OK, Json(json!({ "acknowledged": true }))).into_response()
+}
+
+fn index_not_found(index_name: &str) -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "index_not_found_exception",
+        &format!("no such index [{index_name}]"),
+    )
+}
+
+fn error_response(status: StatusCode, error_type: &str, reason: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "error": { "root_cause": [{ "type": error_type, "reason": reason }], "type": error_type, "reason": reason },
+            "status": status.as_u16(),
+        })),
+    )
+        .into_response()
+}
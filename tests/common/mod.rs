@@ -0,0 +1,5 @@
+// each test crate uses only a part of the helpers
+#![allow(dead_code)]
+
+pub mod fixtures;
+pub mod mock_elasticsearch;
@@ -0,0 +1,253 @@
+mod common;
+
+use common::fixtures::{CategoryFixture, FixtureBuilder, ProductFixture, ROOT_CATEGORY_ID};
+use serde_json::{json, Value};
+use shopsys_sqlx_test::elastic::category_index::CategoryIndex;
+use shopsys_sqlx_test::elastic::export_source::ExportSource;
+use shopsys_sqlx_test::elastic::index::Index;
+use shopsys_sqlx_test::elastic::product_index::ProductIndex;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+/**
+ * Bikes with variants in two categories, the variants differ in flags
+ */
+fn get_fixtures() -> FixtureBuilder {
+    FixtureBuilder::new()
+        .brand(1, "Shimano")
+        .availability(1, 0, &[("cs", "Skladem"), ("en", "In stock")])
+        .parameter(7, &[("cs", "Barva"), ("en", "Color")])
+        .category(
+            CategoryFixture::new(2, ROOT_CATEGORY_ID)
+                .name("cs", "Kola")
+                .name("en", "Bikes")
+                .slug("kola"),
+        )
+        .category(
+            CategoryFixture::new(3, 2)
+                .name("cs", "Horská kola")
+                .name("en", "Mountain bikes")
+                .hidden_on(2),
+        )
+        .category(CategoryFixture::new(4, ROOT_CATEGORY_ID).name("cs", "Doplňky"))
+        .product(
+            ProductFixture::new(10)
+                .full_name("cs", "Horské", "Kolo", "XL")
+                .name("en", "Bike")
+                .partno("P10")
+                .ean("8594000000010")
+                .description("<p>Popis</p>", "Krátký popis")
+                .brand(1)
+                .flags(&[1])
+                .categories(&[2, 3])
+                .slug("horske-kolo")
+                .image(5, "jpg")
+                .image(6, "png")
+                .price(12100.0)
+                .availability(1)
+                .parameter_value(7, "cs", "červená")
+                .parameter_value(7, "en", "red"),
+        )
+        .product(
+            ProductFixture::new(11)
+                .variant_of(10)
+                .flags(&[2])
+                .price(9900.0),
+        )
+        .product(
+            ProductFixture::new(12)
+                .variant_of(10)
+                .flags(&[3])
+                .price(14000.0)
+                .hidden_on(1),
+        )
+        .product(
+            ProductFixture::new(13)
+                .variant_of(10)
+                .flags(&[4])
+                .selling_denied(),
+        )
+        .product(ProductFixture::new(14).name("cs", "Zvonek").hidden_on(1))
+}
+
+/**
+ * Ids from unordered queries are sorted, so documents can be compared as a whole
+ */
+fn get_documents<D: serde::Serialize>(export_data: BTreeMap<i32, D>) -> BTreeMap<i32, Value> {
+    export_data
+        .into_iter()
+        .map(|(id, data)| {
+            let mut document = json!(data);
+            for field in ["flags", "categories"] {
+                if let Some(ids) = document[field].as_array_mut() {
+                    ids.sort_by_key(|id| id.as_i64());
+                }
+            }
+            (id, document)
+        })
+        .collect()
+}
+
+#[sqlx::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn product_export_data(pool: PgPool) {
+    let fixtures = get_fixtures();
+    let product_index = ProductIndex::new(ExportSource::new(pool.clone(), fixtures.get_domains()));
+    fixtures.insert(&pool).await;
+
+    let documents = get_documents(
+        product_index
+            .get_export_data_for_batch(1, 0, 100)
+            .await
+            .unwrap(),
+    );
+
+    assert_eq!(
+        documents.keys().copied().collect::<Vec<i32>>(),
+        vec![10, 11, 13]
+    );
+    // uuids are generated by the fixtures
+    let parameter = &documents[&10]["parameters"][0];
+    assert_eq!(
+        documents[&10],
+        json!({
+            "id": 10,
+            "catnum": "CAT10",
+            "partno": "P10",
+            "ean": "8594000000010",
+            "name": "Kolo",
+            "full_name": "Horské Kolo XL",
+            "description": "<p>Popis</p>",
+            "short_description": "Krátký popis",
+            "brand": 1,
+            // flags of the visible and sellable variants are exported on the main variant
+            "flags": [1, 2],
+            "categories": [2, 3],
+            "slug": "horske-kolo",
+            "image_url": "/content/images/product/original/5.jpg",
+            "availability": "Skladem",
+            "in_stock": true,
+            // filtering prices span the sellable variants visible on the domain
+            "prices": [{
+                "pricing_group_id": 1,
+                "price_with_vat": 12100.0,
+                "filtering_minimal_price": 9900.0,
+                "filtering_maximal_price": 12100.0,
+            }],
+            "parameters": [{
+                "parameter_id": 7,
+                "parameter_uuid": parameter["parameter_uuid"],
+                "parameter_name": "Barva",
+                "parameter_unit": null,
+                "parameter_value_id": parameter["parameter_value_id"],
+                "parameter_value_uuid": parameter["parameter_value_uuid"],
+                "parameter_value_text": "červená",
+            }],
+            // sellable variants visible on the domain
+            "variants": [11],
+            "searching_names": "Horské Kolo XL",
+            "searching_catnums": "CAT10",
+            "searching_partnos": "P10",
+            "searching_eans": "8594000000010",
+            "searching_short_descriptions": "Krátký popis",
+            "searching_descriptions": "<p>Popis</p>",
+        })
+    );
+    assert_eq!(product_index.get_total_count(2).await.unwrap(), 5);
+}
+
+#[sqlx::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn product_export_data_for_ids(pool: PgPool) {
+    let fixtures = get_fixtures();
+    let product_index = ProductIndex::new(ExportSource::new(pool.clone(), fixtures.get_domains()));
+    fixtures.insert(&pool).await;
+
+    let documents = product_index
+        .get_export_data_for_ids(1, vec![10, 14])
+        .await
+        .unwrap();
+
+    // product hidden on the domain is missing, so it is deleted from the index
+    assert_eq!(documents.keys().copied().collect::<Vec<i32>>(), vec![10]);
+}
+
+#[sqlx::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn product_export_data_of_second_domain(pool: PgPool) {
+    let fixtures = get_fixtures();
+    let product_index = ProductIndex::new(ExportSource::new(pool.clone(), fixtures.get_domains()));
+    fixtures.insert(&pool).await;
+
+    let documents = get_documents(
+        product_index
+            .get_export_data_for_ids(2, vec![10, 14])
+            .await
+            .unwrap(),
+    );
+
+    assert_eq!(
+        documents.keys().copied().collect::<Vec<i32>>(),
+        vec![10, 14]
+    );
+    // translation of the domain locale, variant hidden on domain 1 is visible for the pricing group of domain 2
+    assert_eq!(documents[&10]["name"], "Bike");
+    assert_eq!(documents[&10]["flags"], json!([1, 2, 3]));
+    assert_eq!(documents[&10]["variants"], json!([11, 12]));
+    assert_eq!(documents[&10]["availability"], "In stock");
+    assert_eq!(documents[&10]["parameters"][0]["parameter_name"], "Color");
+    assert_eq!(
+        documents[&10]["parameters"][0]["parameter_value_text"],
+        "red"
+    );
+    assert_eq!(
+        documents[&10]["prices"],
+        json!([{
+            "pricing_group_id": 2,
+            "price_with_vat": 12100.0,
+            "filtering_minimal_price": 9900.0,
+            "filtering_maximal_price": 14000.0,
+        }])
+    );
+    assert_eq!(documents[&14]["name"], "");
+    assert_eq!(documents[&14]["in_stock"], false);
+    assert_eq!(documents[&14]["image_url"], Value::Null);
+}
+
+#[sqlx::test]
+#[ignore = "needs Postgres in DATABASE_URL"]
+async fn category_export_data(pool: PgPool) {
+    let fixtures = get_fixtures();
+    let category_index =
+        CategoryIndex::new(ExportSource::new(pool.clone(), fixtures.get_domains()));
+    fixtures.insert(&pool).await;
+
+    let documents = get_documents(
+        category_index
+            .get_export_data_for_batch(2, 0, 100)
+            .await
+            .unwrap(),
+    );
+
+    assert_eq!(
+        documents.keys().copied().collect::<Vec<i32>>(),
+        vec![2, 3, 4]
+    );
+    assert_eq!(documents[&2]["name"], "Bikes");
+    assert_eq!(documents[&2]["slug"], "kola");
+    assert_eq!(
+        [&documents[&2], &documents[&3], &documents[&4]].map(|document| (
+            document["level"].clone(),
+            document["lft"].clone(),
+            document["rgt"].clone()
+        )),
+        [
+            (json!(1), json!(2), json!(5)),
+            (json!(2), json!(3), json!(4)),
+            (json!(1), json!(6), json!(7))
+        ]
+    );
+    assert_eq!(documents[&3]["visible"], false);
+    assert_eq!(documents[&3]["product_count"], 1);
+    assert_eq!(documents[&4]["product_count"], 0);
+}
@@ -0,0 +1,314 @@
+mod common;
+
+use common::mock_elasticsearch::MockElasticsearch;
+use serde_json::{json, Value};
+use shopsys_sqlx_test::elastic::document_diff::DocumentSelection;
+use shopsys_sqlx_test::elastic::error::ElasticError;
+use shopsys_sqlx_test::elastic::index::Index;
+use shopsys_sqlx_test::elastic::index_definition::IndexDefinition;
+use shopsys_sqlx_test::elastic::index_definition_loader::{
+    IndexDefinitionLoader, DEFAULT_DEFINITIONS_DIRECTORY,
+};
+use shopsys_sqlx_test::elastic::index_facade::IndexFacade;
+use shopsys_sqlx_test::elastic::index_repository::IndexRepository;
+use shopsys_sqlx_test::settings::settings::ExportSettings;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/**
+ * Documents of the product index without Postgres, ids have to be ascending as in the database
+ */
+#[derive(Clone)]
+struct InMemoryIndex {
+    documents: Arc<BTreeMap<i32, Value>>,
+}
+
+impl InMemoryIndex {
+    fn new(ids: impl IntoIterator<Item = i32>) -> Self {
+        InMemoryIndex {
+            documents: Arc::new(
+                ids.into_iter()
+                    .map(|id| (id, json!({ "id": id, "name": format!("Product {id}") })))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Index for InMemoryIndex {
+    type Document = Value;
+
+    fn name() -> &'static str {
+        "product"
+    }
+
+    async fn get_total_count(&self, _domain_id: u8) -> Result<i64, ElasticError> {
+        Ok(self.documents.len() as i64)
+    }
+
+    async fn get_export_data_for_batch(
+        &self,
+        _domain_id: u8,
+        last_processed_id: u32,
+        batch_size: u32,
+    ) -> Result<BTreeMap<i32, Value>, ElasticError> {
+        Ok(self
+            .documents
+            .range(last_processed_id as i32 + 1..)
+            .take(batch_size as usize)
+            .map(|(id, document)| (*id, document.clone()))
+            .collect())
+    }
+
+    async fn get_export_data_for_ids(
+        &self,
+        _domain_id: u8,
+        ids: Vec<i32>,
+    ) -> Result<BTreeMap<i32, Value>, ElasticError> {
+        Ok(ids
+            .iter()
+            .filter_map(|id| Some((*id, self.documents.get(id)?.clone())))
+            .collect())
+    }
+}
+
+fn get_product_definition() -> IndexDefinition {
+    IndexDefinitionLoader::new(
+        DEFAULT_DEFINITIONS_DIRECTORY.to_string(),
+        "test".to_string(),
+    )
+    .get_definition(InMemoryIndex::name().to_string(), 1)
+}
+
+fn get_export_settings(batch_size: u32, concurrency: u32) -> ExportSettings {
+    ExportSettings {
+        batch_size,
+        concurrency,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn create_and_delete() {
+    let elasticsearch = MockElasticsearch::start().await;
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade = IndexFacade::new(&index_repository);
+    let index_definition = get_product_definition();
+    let index_name = index_definition.get_versioned_index_name().unwrap();
+
+    index_facade.create(&index_definition).await.unwrap();
+
+    assert_eq!(elasticsearch.get_index_names(), vec![index_name.clone()]);
+    assert_eq!(
+        elasticsearch.get_aliases(&index_name),
+        vec!["test_product_1"]
+    );
+    assert_eq!(
+        elasticsearch.get_definition(&index_name),
+        Some(index_definition.get_definition().unwrap())
+    );
+    assert!(matches!(
+        index_facade.create(&index_definition).await,
+        Err(ElasticError::IndexAlreadyExists)
+    ));
+
+    index_facade.delete(&index_definition).await.unwrap();
+
+    assert!(elasticsearch.get_index_names().is_empty());
+}
+
+#[tokio::test]
+async fn export_creates_index_and_sends_all_batches() {
+    let elasticsearch = MockElasticsearch::start().await;
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade =
+        IndexFacade::new(&index_repository).with_export_settings(&get_export_settings(2, 2));
+    let index_definition = get_product_definition();
+
+    index_facade
+        .export(&InMemoryIndex::new(1..=7), &index_definition)
+        .await
+        .unwrap();
+
+    let documents = elasticsearch.get_documents("test_product_1");
+    assert_eq!(
+        documents.keys().copied().collect::<Vec<i32>>(),
+        vec![1, 2, 3, 4, 5, 6, 7]
+    );
+    assert_eq!(documents[&3], json!({ "id": 3, "name": "Product 3" }));
+    assert_eq!(
+        elasticsearch.get_index_names(),
+        vec![index_definition.get_versioned_index_name().unwrap()]
+    );
+}
+
+#[tokio::test]
+async fn export_sends_every_document_once_when_ids_have_gaps() {
+    let elasticsearch = MockElasticsearch::start().await;
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade =
+        IndexFacade::new(&index_repository).with_export_settings(&get_export_settings(2, 3));
+    let index_definition = get_product_definition();
+    let ids = vec![1, 5, 9, 20, 21, 22, 40, 100];
+
+    index_facade
+        .export(&InMemoryIndex::new(ids.clone()), &index_definition)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        elasticsearch
+            .get_documents("test_product_1")
+            .keys()
+            .copied()
+            .collect::<Vec<i32>>(),
+        ids
+    );
+    assert_eq!(elasticsearch.get_bulk_operation_count(), ids.len());
+}
+
+#[tokio::test]
+async fn export_ids_deletes_documents_no_longer_exported() {
+    let elasticsearch = MockElasticsearch::start().await;
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade = IndexFacade::new(&index_repository);
+    let index_definition = get_product_definition();
+    index_facade
+        .export(&InMemoryIndex::new(1..=3), &index_definition)
+        .await
+        .unwrap();
+
+    index_facade
+        .export_ids(&InMemoryIndex::new([1, 3]), &index_definition, &[2, 3])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        elasticsearch
+            .get_documents("test_product_1")
+            .into_keys()
+            .collect::<Vec<i32>>(),
+        vec![1, 3]
+    );
+}
+
+#[tokio::test]
+async fn diff_of_all_documents_finds_documents_only_in_index() {
+    let elasticsearch = MockElasticsearch::start().await;
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade =
+        IndexFacade::new(&index_repository).with_export_settings(&get_export_settings(2, 1));
+    let index_definition = get_product_definition();
+    index_facade
+        .export(&InMemoryIndex::new(1..=7), &index_definition)
+        .await
+        .unwrap();
+
+    let report = index_facade
+        .diff(
+            &InMemoryIndex::new([1, 2, 3, 5, 8]),
+            &index_definition,
+            &DocumentSelection::All,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(report.compared_count, 4);
+    assert_eq!(report.identical_count, 4);
+    assert_eq!(report.documents_missing_in_index, 1);
+    // stale documents of the index are found by the scroll, the export never reaches them
+    assert_eq!(report.documents_missing_in_export, 3);
+    assert_eq!(report.missing_document_example_ids, vec![8, 4, 6, 7]);
+}
+
+#[tokio::test]
+async fn migrate_reindexes_into_index_of_changed_definition() {
+    let elasticsearch = MockElasticsearch::start().await;
+    elasticsearch.add_index(
+        "test_product_1_outdated",
+        &["test_product_1"],
+        vec![(1, json!({ "id": 1 })), (2, json!({ "id": 2 }))],
+    );
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade = IndexFacade::new(&index_repository);
+    let index_definition = get_product_definition();
+    let index_name = index_definition.get_versioned_index_name().unwrap();
+
+    index_facade.migrate(&index_definition).await.unwrap();
+
+    assert_eq!(elasticsearch.get_index_names(), vec![index_name.clone()]);
+    assert_eq!(
+        elasticsearch.get_aliases(&index_name),
+        vec!["test_product_1"]
+    );
+    assert_eq!(
+        elasticsearch
+            .get_documents("test_product_1")
+            .into_keys()
+            .collect::<Vec<i32>>(),
+        vec![1, 2]
+    );
+
+    // second run has nothing to do
+    index_facade.migrate(&index_definition).await.unwrap();
+    assert_eq!(elasticsearch.get_index_names(), vec![index_name]);
+}
+
+#[tokio::test]
+async fn migrate_deletes_new_index_when_reindex_fails() {
+    let elasticsearch = MockElasticsearch::start().await;
+    elasticsearch.add_index(
+        "test_product_1_outdated",
+        &["test_product_1"],
+        vec![(1, json!({ "id": 1 }))],
+    );
+    elasticsearch.fail_reindex();
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade = IndexFacade::new(&index_repository);
+
+    assert!(matches!(
+        index_facade.migrate(&get_product_definition()).await,
+        Err(ElasticError::BulkItemsFailed(1))
+    ));
+
+    // alias keeps pointing to the outdated index, so the next migrate can start over
+    assert_eq!(
+        elasticsearch.get_index_names(),
+        vec!["test_product_1_outdated"]
+    );
+    assert_eq!(
+        elasticsearch.get_aliases("test_product_1_outdated"),
+        vec!["test_product_1"]
+    );
+}
+
+#[tokio::test]
+async fn status_report_finds_outdated_and_orphaned_indexes() {
+    let elasticsearch = MockElasticsearch::start().await;
+    elasticsearch.add_index("test_product_1_outdated", &["test_product_1"], vec![]);
+    elasticsearch.add_index("test_product_1_leftover", &[], vec![(1, json!({}))]);
+    elasticsearch.add_index("other_index", &[], vec![]);
+    let index_repository = IndexRepository::new(elasticsearch.client());
+    let index_facade = IndexFacade::new(&index_repository);
+
+    let report = index_facade
+        .get_status_report(&[get_product_definition()])
+        .await
+        .unwrap();
+
+    assert_eq!(report.indexes.len(), 1);
+    assert_eq!(
+        report.indexes[0].index.as_deref(),
+        Some("test_product_1_outdated")
+    );
+    assert!(!report.indexes[0].is_up_to_date);
+    assert_eq!(report.indexes[0].docs_count, Some(0));
+    assert_eq!(
+        report
+            .orphaned_indexes
+            .iter()
+            .map(|index_info| index_info.index.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["test_product_1_leftover"]
+    );
+}